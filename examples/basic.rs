@@ -1,14 +1,11 @@
 use bevy_grid_map::prelude::*;
 mod utils;
 use bevy::prelude::*;
-use derive_more::Display;
-use enum2egui::GuiInspect;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use utils::{VoxelVec, setup_camera};
 
 use bevy_vox_scene::VoxScenePlugin;
-use enum2egui::Gui;
 
 fn main() {
     App::new()
@@ -28,7 +25,12 @@ fn main() {
                 y: 8.0,
                 z: 16.0,
             })),))
-        .add_observer(on_add_tile_type)
+        // Spawns the scene of each tile
+        .add_plugins(TileVisualsPlugin::<TileType, ()>::new().with_function(tile_visual))
+        // Shapes grass placed with the "Auto Tile" brush into slopes
+        .add_plugins(AutoTilePlugin::<TileType, ()>::new(grass_rules()))
+        // Used by the editor's "Generate" panel
+        .insert_resource(terrain())
         .run();
 }
 
@@ -37,20 +39,17 @@ fn main() {
 // ----------------------------- //
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)] // Default
-#[derive(Component, Gui, Display)] // egui + bevy
+#[derive(Component, Reflect)] // bevy + editor
 pub enum TileType {
     Oil,
     Cement,
     Stone,
     Tree,
-    #[display("Grass")]
-    Grass {
-        slop: SlopType,
-    },
+    Grass { slop: SlopType },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)] // Default
-#[derive(Component, Gui, Display, Default)] // egui + bevy
+#[derive(Component, Reflect, Default)] // bevy + editor
 pub enum SlopType {
     #[default]
     Flat,
@@ -68,17 +67,51 @@ impl Default for TileType {
     }
 }
 
-pub fn on_add_tile_type(
-    event: On<Add, TileType>,
-    mut commands: Commands,
-    asset_sever: Res<AssetServer>,
-    tiles: Query<(&TileType, &Tile<()>)>,
-    tile_map: Res<TileMap<()>>,
-) {
-    let Ok((typ, tile)) = tiles.get(event.entity) else {
-        return;
-    };
+/// Grass slopes up towards higher neighbors. The rules are written for a slope rising towards +X
+/// and are rotated to fit the other sides.
+pub fn grass_rules() -> Vec<AutoTileRule<TileType>> {
+    let grass = |slop| TileType::Grass { slop };
+    let is_grass = |typ: &TileType| matches!(typ, TileType::Grass { .. });
+    let up = IVec3::Y;
+    vec![
+        AutoTileRule::new(grass(SlopType::Concave))
+            .with_neighbor(up, NeighborRule::Empty)
+            .with_neighbor(IVec3::X + up, NeighborRule::Occupied)
+            .with_neighbor(IVec3::Z + up, NeighborRule::Occupied),
+        AutoTileRule::new(grass(SlopType::Straight))
+            .with_neighbor(up, NeighborRule::Empty)
+            .with_neighbor(IVec3::X + up, NeighborRule::Occupied),
+        AutoTileRule::new(grass(SlopType::Convex))
+            .with_neighbor(up, NeighborRule::Empty)
+            .with_neighbor(IVec3::X + IVec3::Z + up, NeighborRule::Occupied)
+            .with_neighbor(IVec3::X + up, NeighborRule::Empty)
+            .with_neighbor(IVec3::Z + up, NeighborRule::Empty),
+        AutoTileRule::new(grass(SlopType::Flat)),
+    ]
+    .into_iter()
+    .map(|rule| rule.for_types(is_grass))
+    .collect()
+}
+
+/// Grass hills with stone cliffs, and oil lakes in the valleys of the wetter biome.
+pub fn terrain() -> TerrainGenerator<TileType> {
+    let grass = |slop| TileType::Grass { slop };
+    let hills = vec![
+        TerrainBand::new(grass(SlopType::Flat)).with_slopes(0..=0),
+        TerrainBand::new(grass(SlopType::Straight))
+            .with_slopes(1..=1)
+            .with_orientation(TerrainOrientation::Uphill),
+        TerrainBand::new(TileType::Stone),
+    ];
+    let mut wetlands = vec![TerrainBand::new(TileType::Oil).with_heights(i32::MIN..=1)];
+    wetlands.extend(hills.iter().cloned());
+    TerrainGenerator::new(0)
+        .with_biome(0.5, hills)
+        .with_biome(1.0, wetlands)
+        .with_fill(TileType::Cement)
+}
 
+pub fn tile_visual(typ: &TileType, asset_server: &AssetServer) -> Option<TileVisual> {
     let (scene_path, delta_pos) = match typ {
         TileType::Oil => ("terrain.vox#Oil", Vec3::ZERO),
         TileType::Cement => ("terrain.vox#Flat-Stone", Vec3::ZERO),
@@ -91,15 +124,8 @@ pub fn on_add_tile_type(
             SlopType::Concave => ("terrain.vox#Grass-Concave", Vec3::ZERO),
         },
     };
-    let scene = SceneRoot(asset_sever.load(scene_path));
-    let rotation = tile_map.orientation_to_rotation(tile.orientation);
-    commands.entity(event.entity).insert((
-        scene,
-        Visibility::Visible,
-        Transform::default()
-            .with_rotation(rotation)
-            .with_translation(
-                tile.map_pos.as_vec3() * VoxelVec::voxel((16, 8, 16)) + delta_pos + VoxelVec::y(8),
-            ),
-    ));
+    Some(
+        TileVisual::scene(asset_server.load(scene_path))
+            .with_translation(delta_pos + VoxelVec::y(8)),
+    )
 }
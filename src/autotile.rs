@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::events::{TileChanged, TilePlaced, TileRemoved};
+use crate::map_commands::TileMapCommands;
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+use crate::utils;
+
+type TypePredicate<TYP> = Arc<dyn Fn(&TYP) -> bool + Send + Sync>;
+
+/// What an [`AutoTileRule`] expects in a neighboring cell.
+#[derive(Clone)]
+pub enum NeighborRule<TYP: TileTypeAble> {
+    Any,
+    Occupied,
+    Empty,
+    /// Occupied by a tile whose type matches the predicate.
+    Matches(TypePredicate<TYP>),
+}
+impl<TYP: TileTypeAble> NeighborRule<TYP> {
+    pub fn matches(predicate: impl Fn(&TYP) -> bool + Send + Sync + 'static) -> Self {
+        Self::Matches(Arc::new(predicate))
+    }
+    fn check(&self, neighbor: Option<&TYP>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Occupied => neighbor.is_some(),
+            Self::Empty => neighbor.is_none(),
+            Self::Matches(predicate) => neighbor.is_some_and(|typ| predicate(typ)),
+        }
+    }
+}
+
+/// Turns an auto tiled cell into `output` when its neighbors match.
+///
+/// Neighbor offsets are written for orientation `0`. The rule is also tried rotated by every
+/// other orientation of [`TileMapConfig::possible_orientations`](crate::resources::TileMapConfig),
+/// and the tile gets the orientation it matched under.
+#[derive(Clone)]
+pub struct AutoTileRule<TYP: TileTypeAble> {
+    pub neighbors: Vec<(IVec3, NeighborRule<TYP>)>,
+    pub output: TYP,
+    /// Types the rule may change, all types if `None`.
+    pub applies_to: Option<TypePredicate<TYP>>,
+}
+impl<TYP: TileTypeAble> AutoTileRule<TYP> {
+    pub fn new(output: TYP) -> Self {
+        Self {
+            neighbors: Vec::new(),
+            output,
+            applies_to: None,
+        }
+    }
+    pub fn with_neighbor(mut self, offset: IVec3, rule: NeighborRule<TYP>) -> Self {
+        self.neighbors.push((offset, rule));
+        self
+    }
+    pub fn for_types(mut self, predicate: impl Fn(&TYP) -> bool + Send + Sync + 'static) -> Self {
+        self.applies_to = Some(Arc::new(predicate));
+        self
+    }
+}
+
+/// Marks a tile whose type and orientation are picked by the [`AutoTileRules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct AutoTiled<MARKER: MarkerAble>(PhantomData<MARKER>);
+impl<MARKER: MarkerAble> Default for AutoTiled<MARKER> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Ordered auto tiling rules, the first matching rule wins.
+#[derive(Resource)]
+pub struct AutoTileRules<TYP: TileTypeAble, MARKER: MarkerAble> {
+    pub rules: Vec<AutoTileRule<TYP>>,
+    /// Cells that changed since the rules were last applied.
+    pending: HashSet<IVec3>,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> AutoTileRules<TYP, MARKER> {
+    pub fn new(rules: Vec<AutoTileRule<TYP>>) -> Self {
+        Self {
+            rules,
+            pending: HashSet::new(),
+            _marker: PhantomData,
+        }
+    }
+    /// Re-evaluates the cell at `map_pos` and every cell that has it as neighbor.
+    pub fn mark_changed(&mut self, map_pos: IVec3) {
+        self.pending.insert(map_pos);
+    }
+
+    /// Type and orientation the first matching rule gives the cell at `map_pos`.
+    pub fn evaluate(
+        &self,
+        tile_map: &TileMap<MARKER>,
+        map_pos: IVec3,
+        typ: &TYP,
+        neighbor: impl Fn(IVec3) -> Option<TYP>,
+    ) -> Option<(TYP, u8)> {
+        let orientations = &tile_map.config.possible_orientations;
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to.as_ref().is_none_or(|applies| applies(typ)))
+            .find_map(|rule| {
+                (0..orientations.len()).find_map(|orientation| {
+                    let rotation = orientations[orientation];
+                    let matched = rule.neighbors.iter().all(|(offset, neighbor_rule)| {
+                        neighbor_rule.check(
+                            neighbor(map_pos + tile_map.config.rotate_offset(rotation, *offset))
+                                .as_ref(),
+                        )
+                    });
+                    matched.then(|| (rule.output.clone(), orientation as u8))
+                })
+            })
+    }
+
+    /// Every offset a rule looks at, under every orientation.
+    fn reach(&self, tile_map: &TileMap<MARKER>) -> HashSet<IVec3> {
+        let mut reach = HashSet::from([IVec3::ZERO]);
+        for rotation in tile_map.config.possible_orientations.iter() {
+            for rule in self.rules.iter() {
+                reach.extend(
+                    rule.neighbors
+                        .iter()
+                        .map(|(offset, _)| tile_map.config.rotate_offset(*rotation, *offset)),
+                );
+            }
+        }
+        reach
+    }
+
+    fn on_tile_placed(event: On<TilePlaced<TYP, MARKER>>, mut rules: ResMut<Self>) {
+        rules.mark_changed(event.map_pos);
+    }
+    fn on_tile_removed(event: On<TileRemoved<TYP, MARKER>>, mut rules: ResMut<Self>) {
+        rules.mark_changed(event.map_pos);
+    }
+    fn on_tile_changed(event: On<TileChanged<TYP, MARKER>>, mut rules: ResMut<Self>) {
+        rules.mark_changed(event.map_pos);
+    }
+
+    /// Applies the rules to the auto tiled cells around changed cells. Changes made here trigger
+    /// [`TileChanged`], so their neighbors are updated in the next frame.
+    fn update(
+        mut rules: ResMut<Self>,
+        auto_tiled: Query<(), With<AutoTiled<MARKER>>>,
+        mut map_commands: TileMapCommands<TYP, MARKER>,
+    ) {
+        if rules.pending.is_empty() {
+            return;
+        }
+        let reach = rules.reach(map_commands.tile_map());
+        let cells: HashSet<IVec3> = rules
+            .pending
+            .drain()
+            .flat_map(|map_pos| reach.iter().map(move |offset| map_pos - *offset))
+            .collect();
+
+        let mut changes = Vec::new();
+        for map_pos in cells {
+            let Some(entity) = map_commands.get(map_pos) else {
+                continue;
+            };
+            if !auto_tiled.contains(entity) {
+                continue;
+            }
+            let Some((tile, typ)) = map_commands.get_tile(map_pos) else {
+                continue;
+            };
+            let neighbor =
+                |map_pos: IVec3| map_commands.get_tile(map_pos).map(|(_, typ)| typ.clone());
+            let Some((new_typ, orientation)) =
+                rules.evaluate(map_commands.tile_map(), map_pos, typ, neighbor)
+            else {
+                continue;
+            };
+            if orientation != tile.orientation || utils::type_key(&new_typ) != utils::type_key(typ)
+            {
+                changes.push((map_pos, new_typ, orientation));
+            }
+        }
+        for (map_pos, typ, orientation) in changes {
+            map_commands.replace(map_pos, typ, orientation);
+        }
+    }
+}
+
+/// Picks type and orientation of [`AutoTiled`] tiles from their neighbors whenever tiles are
+/// placed, removed or changed.
+pub struct AutoTilePlugin<TYP: TileTypeAble, MARKER: MarkerAble> {
+    rules: Vec<AutoTileRule<TYP>>,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> AutoTilePlugin<TYP, MARKER> {
+    pub fn new(rules: Vec<AutoTileRule<TYP>>) -> Self {
+        Self {
+            rules,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for AutoTilePlugin<TYP, MARKER> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AutoTileRules::<TYP, MARKER>::new(self.rules.clone()));
+        app.add_observer(AutoTileRules::<TYP, MARKER>::on_tile_placed);
+        app.add_observer(AutoTileRules::<TYP, MARKER>::on_tile_removed);
+        app.add_observer(AutoTileRules::<TYP, MARKER>::on_tile_changed);
+        app.add_systems(Update, AutoTileRules::<TYP, MARKER>::update);
+    }
+}
@@ -0,0 +1,315 @@
+//! Command line tool for map files: converting, validating, statistics and diffs.
+//!
+//! Tile types are read as plain Json values, so it works on maps of any tile type.
+
+use bevy::prelude::*;
+use bevy_grid_map::io::{self, MapFile, SkippedEntry, TileRecord};
+use bevy_grid_map::prelude::{FileFormat, TileMapConfig};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+  bevy_grid_map convert <input> <output> [--from <format>] [--to <format>]
+  bevy_grid_map validate <file> [--format <format>] [--orientations <count> | --config <file>]
+  bevy_grid_map stats <file> [--format <format>]
+  bevy_grid_map diff <old> <new> [--format <format>]
+
+Formats are taken from the file extensions unless given: csv, json.
+Exits with 1 if validate finds problems or diff finds differences, 2 on errors.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match Args::parse(&args) {
+        Ok(args) => run(&args),
+        Err(e) => Err(format!("{}\n\n{}", e, USAGE)),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Runs the command, `false` if it found problems or differences.
+fn run(args: &Args) -> Result<bool, String> {
+    match args.command.as_str() {
+        "convert" => convert(args).map(|_| true),
+        "validate" => validate(args),
+        "stats" => stats(args).map(|_| true),
+        "diff" => diff(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        command => Err(format!("unknown command \"{}\"\n\n{}", command, USAGE)),
+    }
+}
+
+/// Command, positional paths and `--name value` options.
+struct Args {
+    command: String,
+    paths: Vec<PathBuf>,
+    options: HashMap<String, String>,
+}
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((command, rest)) = args.split_first() else {
+            return Err("missing command".into());
+        };
+        let mut paths = Vec::new();
+        let mut options = HashMap::new();
+        let mut rest = rest.iter();
+        while let Some(arg) = rest.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = rest.next().ok_or(format!("--{} needs a value", name))?;
+                    options.insert(name.to_string(), value.clone());
+                }
+                None => paths.push(PathBuf::from(arg)),
+            }
+        }
+        Ok(Self {
+            command: command.clone(),
+            paths,
+            options,
+        })
+    }
+    fn path(&self, index: usize, name: &str) -> Result<&Path, String> {
+        self.paths
+            .get(index)
+            .map(PathBuf::as_path)
+            .ok_or(format!("missing <{}>", name))
+    }
+    /// Format named by the option `name`, or by the extension of `path`.
+    fn format(&self, name: &str, path: &Path) -> Result<FileFormat, String> {
+        match self.options.get(name).map(String::as_str) {
+            Some("csv") => Ok(FileFormat::Csv),
+            Some("json") => Ok(FileFormat::Json),
+            Some(format) => Err(format!("unknown format \"{}\"", format)),
+            None => io::format_of(path).ok_or(format!(
+                "can't tell the format of {:?}, pass --{}",
+                path, name
+            )),
+        }
+    }
+    /// Number of orientations tiles may use, from `--orientations` or the config in `--config`.
+    fn orientation_count(&self) -> Result<usize, String> {
+        if let Some(count) = self.options.get("orientations") {
+            return count
+                .parse()
+                .map_err(|_| format!("invalid orientation count \"{}\"", count));
+        }
+        let Some(path) = self.options.get("config") else {
+            return Ok(TileMapConfig::default().possible_orientations.len());
+        };
+        let file = std::fs::File::open(path).map_err(|e| format!("{} ({})", e, path))?;
+        let config: TileMapConfig =
+            serde_json::from_reader(file).map_err(|e| format!("invalid config {}: {}", path, e))?;
+        Ok(config.possible_orientations.len())
+    }
+}
+
+/// Reads a map the same way [`LoadMapEvent`](bevy_grid_map::prelude::LoadMapEvent) does.
+fn read(
+    args: &Args,
+    path: &Path,
+    format_option: &str,
+) -> Result<(MapFile<Value>, Vec<SkippedEntry>), String> {
+    let format = args.format(format_option, path)?;
+    io::read_map_from_file_checked(path, format)
+}
+
+fn convert(args: &Args) -> Result<(), String> {
+    let input = args.path(0, "input")?;
+    let output = args.path(1, "output")?;
+    let format = args.format("to", output)?;
+    let (map, skipped) = read(args, input, "from")?;
+    for entry in skipped.iter() {
+        eprintln!("skipped {}: {}", entry.location, entry.reason);
+    }
+    io::write_map_to_file(output, format, map.seed, &map.tiles)?;
+    println!(
+        "converted {} tiles from {:?} to {:?}",
+        map.tiles.len(),
+        input,
+        output
+    );
+    Ok(())
+}
+
+fn validate(args: &Args) -> Result<bool, String> {
+    let path = args.path(0, "file")?;
+    let orientation_count = args.orientation_count()?;
+    let (map, skipped) = read(args, path, "format")?;
+
+    let mut problems: Vec<String> = skipped
+        .iter()
+        .map(|entry| format!("{}: {}", entry.location, entry.reason))
+        .collect();
+
+    let mut counts: BTreeMap<[i32; 3], usize> = BTreeMap::new();
+    map.tiles.iter().for_each(|tile| {
+        *counts.entry(tile.map_pos.to_array()).or_default() += 1;
+    });
+    problems.extend(
+        counts
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(map_pos, count)| format!("{} tiles at {:?}", count, map_pos)),
+    );
+    problems.extend(
+        map.tiles
+            .iter()
+            .filter(|tile| tile.orientation as usize >= orientation_count)
+            .map(|tile| {
+                format!(
+                    "tile at {:?} has orientation {}, the config has {}",
+                    tile.map_pos.to_array(),
+                    tile.orientation,
+                    orientation_count
+                )
+            }),
+    );
+
+    problems.iter().for_each(|problem| println!("{}", problem));
+    println!(
+        "{:?}: {} tiles, {} problems",
+        path,
+        map.tiles.len(),
+        problems.len()
+    );
+    Ok(problems.is_empty())
+}
+
+fn stats(args: &Args) -> Result<(), String> {
+    let path = args.path(0, "file")?;
+    let (map, skipped) = read(args, path, "format")?;
+
+    println!("tiles: {}", map.tiles.len());
+    match map.seed {
+        Some(seed) => println!("seed: {}", seed),
+        None => println!("seed: none"),
+    }
+    if !skipped.is_empty() {
+        println!("unparseable entries: {}", skipped.len());
+    }
+    let positions = map.tiles.iter().map(|tile| tile.map_pos);
+    if let Some(min) = positions.clone().reduce(IVec3::min)
+        && let Some(max) = positions.reduce(IVec3::max)
+    {
+        println!(
+            "bounds: {:?} to {:?}, size {:?}",
+            min.to_array(),
+            max.to_array(),
+            (max - min + IVec3::ONE).to_array()
+        );
+    }
+
+    let mut types: BTreeMap<String, usize> = BTreeMap::new();
+    let mut orientations: BTreeMap<u8, usize> = BTreeMap::new();
+    let mut data_keys: BTreeMap<String, usize> = BTreeMap::new();
+    map.tiles.iter().for_each(|tile| {
+        *types.entry(io::variant_name(&tile.typ)).or_default() += 1;
+        *orientations.entry(tile.orientation).or_default() += 1;
+        tile.data.keys().for_each(|key| {
+            *data_keys.entry(key.clone()).or_default() += 1;
+        });
+    });
+    print_counts("types", types);
+    print_counts("orientations", orientations);
+    print_counts("data", data_keys);
+    Ok(())
+}
+/// Prints `counts` under `title`, the most common first.
+fn print_counts<K: std::fmt::Display>(title: &str, counts: BTreeMap<K, usize>) {
+    if counts.is_empty() {
+        return;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    println!("{}:", title);
+    counts
+        .iter()
+        .for_each(|(key, count)| println!("  {:>8}  {}", count, key));
+}
+
+fn diff(args: &Args) -> Result<bool, String> {
+    let (old, old_skipped) = read(args, args.path(0, "old")?, "format")?;
+    let (new, new_skipped) = read(args, args.path(1, "new")?, "format")?;
+    if !old_skipped.is_empty() || !new_skipped.is_empty() {
+        eprintln!(
+            "ignoring {} unparseable entries of <old> and {} of <new>",
+            old_skipped.len(),
+            new_skipped.len()
+        );
+    }
+
+    // Later tiles replace earlier ones in the same cell, like when loading
+    let by_pos = |map: MapFile<Value>| -> BTreeMap<[i32; 3], TileRecord<Value>> {
+        map.tiles
+            .into_iter()
+            .map(|tile| (tile.map_pos.to_array(), tile))
+            .collect()
+    };
+    let mut differences = 0;
+    if old.seed != new.seed {
+        println!("seed: {:?} -> {:?}", old.seed, new.seed);
+        differences += 1;
+    }
+    let (old, new) = (by_pos(old), by_pos(new));
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for (map_pos, tile) in old.iter() {
+        match new.get(map_pos) {
+            None => {
+                println!("- {:?} {}", map_pos, describe(tile));
+                removed += 1;
+            }
+            Some(new_tile) if new_tile != tile => {
+                println!("~ {:?} {}", map_pos, describe_change(tile, new_tile));
+                changed += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    for (map_pos, tile) in new
+        .iter()
+        .filter(|(map_pos, _)| !old.contains_key(*map_pos))
+    {
+        println!("+ {:?} {}", map_pos, describe(tile));
+        added += 1;
+    }
+    differences += added + removed + changed;
+    println!("{} added, {} removed, {} changed", added, removed, changed);
+    Ok(differences == 0)
+}
+
+fn describe(tile: &TileRecord<Value>) -> String {
+    let mut description = format!("{} dir {}", tile.typ, tile.orientation);
+    if !tile.data.is_empty() {
+        description += &format!(" data {}", Value::from_iter(tile.data.clone()));
+    }
+    description
+}
+fn describe_change(old: &TileRecord<Value>, new: &TileRecord<Value>) -> String {
+    let mut changes = Vec::new();
+    if old.typ != new.typ {
+        changes.push(format!("typ {} -> {}", old.typ, new.typ));
+    }
+    if old.orientation != new.orientation {
+        changes.push(format!("dir {} -> {}", old.orientation, new.orientation));
+    }
+    if old.data != new.data {
+        changes.push(format!(
+            "data {} -> {}",
+            Value::from_iter(old.data.clone()),
+            Value::from_iter(new.data.clone())
+        ));
+    }
+    changes.join(", ")
+}
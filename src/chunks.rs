@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::components::{Tile, TileOfMap};
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+
+/// Tiles inside one chunk of the map.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TileChunk {
+    pub tiles: HashMap<IVec3, Entity>,
+    /// Set whenever a tile of this chunk is added, removed or changed.
+    pub dirty: bool,
+    /// Increased on every change, so each consumer can tell which chunks changed since it last
+    /// looked without resetting `dirty` for the others.
+    pub generation: u64,
+}
+impl TileChunk {
+    fn touch(&mut self) {
+        self.dirty = true;
+        self.generation += 1;
+    }
+}
+
+/// Tile index split into chunks of `chunk_size` cells.
+///
+/// Offers the same lookups as a plain `HashMap<IVec3, Entity>` on top of the chunks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkedTiles {
+    chunk_size: IVec3,
+    chunks: HashMap<IVec3, TileChunk>,
+    len: usize,
+}
+impl ChunkedTiles {
+    pub fn new(chunk_size: IVec3) -> Self {
+        Self {
+            chunk_size: chunk_size.max(IVec3::ONE),
+            chunks: HashMap::new(),
+            len: 0,
+        }
+    }
+    pub fn chunk_size(&self) -> IVec3 {
+        self.chunk_size
+    }
+    pub fn chunk_of(&self, map_pos: IVec3) -> IVec3 {
+        map_pos.div_euclid(self.chunk_size)
+    }
+    /// Smallest and largest map position covered by `chunk`.
+    pub fn chunk_bounds(&self, chunk: IVec3) -> (IVec3, IVec3) {
+        let min = chunk * self.chunk_size;
+        (min, min + self.chunk_size - IVec3::ONE)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn get(&self, map_pos: &IVec3) -> Option<&Entity> {
+        self.chunks
+            .get(&self.chunk_of(*map_pos))?
+            .tiles
+            .get(map_pos)
+    }
+    pub fn contains_key(&self, map_pos: &IVec3) -> bool {
+        self.get(map_pos).is_some()
+    }
+    pub fn insert(&mut self, map_pos: IVec3, entity: Entity) -> Option<Entity> {
+        let chunk = self.chunks.entry(self.chunk_of(map_pos)).or_default();
+        chunk.touch();
+        let old = chunk.tiles.insert(map_pos, entity);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+    pub fn remove(&mut self, map_pos: &IVec3) -> Option<Entity> {
+        let chunk_pos = self.chunk_of(*map_pos);
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+        let old = chunk.tiles.remove(map_pos)?;
+        chunk.touch();
+        self.len -= 1;
+        Some(old)
+    }
+    pub fn clear(&mut self) {
+        self.chunks.values_mut().for_each(|chunk| {
+            chunk.tiles.clear();
+            chunk.touch();
+        });
+        self.len = 0;
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&IVec3, &Entity)> {
+        self.chunks.values().flat_map(|chunk| chunk.tiles.iter())
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &IVec3> {
+        self.iter().map(|(pos, _)| pos)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &Entity> {
+        self.iter().map(|(_, entity)| entity)
+    }
+
+    pub fn chunk(&self, chunk: IVec3) -> Option<&TileChunk> {
+        self.chunks.get(&chunk)
+    }
+    /// All chunks, including emptied ones that are still waiting to be cleaned up.
+    pub fn chunks(&self) -> impl Iterator<Item = (&IVec3, &TileChunk)> {
+        self.chunks.iter()
+    }
+    /// Chunks with at least one tile inside the box `min..=max` of chunk coordinates.
+    pub fn chunks_in_box(
+        &self,
+        min: IVec3,
+        max: IVec3,
+    ) -> impl Iterator<Item = (&IVec3, &TileChunk)> {
+        self.chunks.iter().filter(move |(pos, chunk)| {
+            !chunk.tiles.is_empty() && pos.cmpge(min).all() && pos.cmple(max).all()
+        })
+    }
+    pub fn tiles_in_chunk(&self, chunk: IVec3) -> impl Iterator<Item = (&IVec3, &Entity)> {
+        self.chunks
+            .get(&chunk)
+            .into_iter()
+            .flat_map(|chunk| chunk.tiles.iter())
+    }
+
+    pub fn mark_dirty(&mut self, map_pos: IVec3) {
+        if let Some(chunk) = self.chunks.get_mut(&self.chunk_of(map_pos)) {
+            chunk.touch();
+        }
+    }
+    pub fn dirty_chunks(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.dirty)
+            .map(|(pos, _)| *pos)
+    }
+    /// Returns the dirty chunks and resets their flag. Empty chunks are dropped afterwards.
+    pub fn take_dirty_chunks(&mut self) -> Vec<IVec3> {
+        let dirty: Vec<IVec3> = self.dirty_chunks().collect();
+        dirty
+            .iter()
+            .for_each(|pos| self.chunks.get_mut(pos).unwrap().dirty = false);
+        self.chunks.retain(|_, chunk| !chunk.tiles.is_empty());
+        dirty
+    }
+}
+
+type ChangedTiles<'w, 's, MARKER, F> =
+    Query<'w, 's, (&'static Tile<MARKER>, Option<&'static TileOfMap>), F>;
+
+/// Marks the chunks of tiles whose type or orientation changed in place as dirty, in the index
+/// of their map entity for [`TileOfMap`] tiles and of the resource otherwise.
+pub fn mark_changed_chunks<TYP: TileTypeAble, MARKER: MarkerAble>(
+    changed_types: ChangedTiles<MARKER, Changed<TYP>>,
+    changed_tiles: ChangedTiles<MARKER, Changed<Tile<MARKER>>>,
+    mut tile_map: ResMut<TileMap<MARKER>>,
+    mut maps: Query<&mut TileMap<MARKER>>,
+) {
+    changed_types
+        .iter()
+        .chain(changed_tiles.iter())
+        .for_each(|(tile, tile_of)| match tile_of {
+            Some(TileOfMap(map)) => {
+                if let Ok(mut map) = maps.get_mut(*map) {
+                    map.tiles.mark_dirty(tile.map_pos);
+                }
+            }
+            // Only borrowed mutably here, so map entity edits leave the resource unchanged
+            None => tile_map.tiles.mark_dirty(tile.map_pos),
+        });
+}
+
+/// Add to an entity to receive [`ChunkEnteredEvent`] and [`ChunkLeftEvent`] while it moves
+/// through the map.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct ChunkTracker<MARKER: MarkerAble> {
+    pub chunk: Option<IVec3>,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> Default for ChunkTracker<MARKER> {
+    fn default() -> Self {
+        Self {
+            chunk: None,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<MARKER: MarkerAble> ChunkTracker<MARKER> {
+    pub fn update(
+        mut trackers: Query<(Entity, &GlobalTransform, &mut Self)>,
+        tile_map: Res<TileMap<MARKER>>,
+        mut commands: Commands,
+    ) {
+        trackers
+            .iter_mut()
+            .for_each(|(entity, transform, mut tracker)| {
+                let map_pos = tile_map.world_to_map(transform.translation());
+                let chunk = tile_map.tiles.chunk_of(map_pos);
+                if tracker.chunk == Some(chunk) {
+                    return;
+                }
+                if let Some(old) = tracker.chunk {
+                    commands.trigger(ChunkLeftEvent::<MARKER>::new(entity, old));
+                }
+                commands.trigger(ChunkEnteredEvent::<MARKER>::new(entity, chunk));
+                tracker.chunk = Some(chunk);
+            });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct ChunkEnteredEvent<MARKER: MarkerAble> {
+    pub entity: Entity,
+    pub chunk: IVec3,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> ChunkEnteredEvent<MARKER> {
+    pub fn new(entity: Entity, chunk: IVec3) -> Self {
+        Self {
+            entity,
+            chunk,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct ChunkLeftEvent<MARKER: MarkerAble> {
+    pub entity: Entity,
+    pub chunk: IVec3,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> ChunkLeftEvent<MARKER> {
+    pub fn new(entity: Entity, chunk: IVec3) -> Self {
+        Self {
+            entity,
+            chunk,
+            _marker: PhantomData,
+        }
+    }
+}
@@ -17,13 +17,167 @@ impl<MARKER: MarkerAble> Tile<MARKER> {
             _marker: PhantomData,
         }
     }
-    pub fn on_added(
-        event: On<Add, Self>,
-        tiles: Query<&Self>,
+    /// Keeps the index in sync when a tile is spawned or its `Tile` is overwritten.
+    pub fn on_inserted(
+        event: On<Insert, Self>,
+        tiles: Query<(&Self, Option<&TileOfMap>)>,
+        tile_map: ResMut<TileMap<MARKER>>,
+        mut maps: Query<&mut TileMap<MARKER>>,
+    ) {
+        let entity = event.entity;
+        let (tile, tile_of) = tiles.get(entity).unwrap();
+        let mut tile_map = match tile_of {
+            Some(TileOfMap(map)) => match maps.get_mut(*map) {
+                Ok(tile_map) => tile_map,
+                Err(_) => return,
+            },
+            None => tile_map.into(),
+        };
+        if let Some(old) = tile_map.tiles.insert(tile.map_pos, entity)
+            && old != entity
+        {
+            println!(
+                "tile {} took over map position {} from tile {}, use TileMapCommands to handle occupied cells",
+                entity, tile.map_pos, old
+            );
+        }
+    }
+    pub fn on_replaced(
+        event: On<Replace, Self>,
+        tiles: Query<(&Self, Option<&TileOfMap>)>,
+        tile_map: ResMut<TileMap<MARKER>>,
+        mut maps: Query<&mut TileMap<MARKER>>,
+    ) {
+        let entity = event.entity;
+        let (tile, tile_of) = tiles.get(entity).unwrap();
+        let mut tile_map = match tile_of {
+            Some(TileOfMap(map)) => match maps.get_mut(*map) {
+                Ok(tile_map) => tile_map,
+                Err(_) => return,
+            },
+            None => tile_map.into(),
+        };
+        if tile_map.tiles.get(&tile.map_pos) == Some(&entity) {
+            tile_map.tiles.remove(&tile.map_pos);
+        }
+    }
+    /// Moves the tile from the index of the resource to the one of its map entity when
+    /// [`TileOfMap`] is added to an existing tile.
+    pub fn on_map_set(
+        event: On<Insert, TileOfMap>,
+        tiles: Query<(&Self, &TileOfMap)>,
         mut tile_map: ResMut<TileMap<MARKER>>,
+        mut maps: Query<&mut TileMap<MARKER>>,
+        mut commands: Commands,
+    ) {
+        let entity = event.entity;
+        let Ok((tile, TileOfMap(map))) = tiles.get(entity) else {
+            return;
+        };
+        if tile_map.tiles.get(&tile.map_pos) == Some(&entity) {
+            tile_map.tiles.remove(&tile.map_pos);
+        }
+        if let Ok(mut map_component) = maps.get_mut(*map) {
+            map_component.tiles.insert(tile.map_pos, entity);
+        }
+        commands.entity(entity).insert(ChildOf(*map));
+    }
+    /// Drops the tile from the index of its map entity when it leaves it.
+    pub fn on_map_unset(
+        event: On<Replace, TileOfMap>,
+        tiles: Query<(&Self, &TileOfMap)>,
+        mut maps: Query<&mut TileMap<MARKER>>,
     ) {
         let entity = event.entity;
-        let tile = tiles.get(entity).unwrap();
-        tile_map.tiles.insert(tile.map_pos, entity);
+        let Ok((tile, TileOfMap(map))) = tiles.get(entity) else {
+            return;
+        };
+        if let Ok(mut tile_map) = maps.get_mut(*map)
+            && tile_map.tiles.get(&tile.map_pos) == Some(&entity)
+        {
+            tile_map.tiles.remove(&tile.map_pos);
+        }
+    }
+}
+
+/// Entity the map is placed by: its `GlobalTransform` moves, turns and scales the whole map
+/// and tiles become its children. Without one the map sits at the world origin. Use at most
+/// one root per `MARKER`.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+#[require(Transform)]
+#[cfg_attr(feature = "render", require(Visibility))]
+pub struct TileMapRoot<MARKER: MarkerAble> {
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> Default for TileMapRoot<MARKER> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+impl<MARKER: MarkerAble> TileMapRoot<MARKER> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Parents new tiles of the resource map to the root.
+    pub fn on_tile_added(
+        event: On<Add, Tile<MARKER>>,
+        roots: Query<Entity, With<Self>>,
+        map_tiles: Query<(), With<TileOfMap>>,
+        mut commands: Commands,
+    ) {
+        if map_tiles.contains(event.entity) {
+            return;
+        }
+        if let Some(root) = roots.iter().next() {
+            commands.entity(event.entity).insert(ChildOf(root));
+        }
+    }
+    /// Parents the existing tiles of the resource map to a new root.
+    pub fn on_root_added(
+        event: On<Add, Self>,
+        tiles: Query<Entity, (With<Tile<MARKER>>, Without<TileOfMap>)>,
+        mut commands: Commands,
+    ) {
+        for tile in tiles.iter() {
+            commands.entity(tile).insert(ChildOf(event.entity));
+        }
+    }
+    /// Copies the root and its transform to [`TileMap::root`] and [`TileMap::origin`].
+    pub fn sync_origin(
+        roots: Query<(Entity, &GlobalTransform), With<Self>>,
+        mut tile_map: ResMut<TileMap<MARKER>>,
+    ) {
+        let root = roots.iter().next();
+        let origin = root.map(|(_, origin)| *origin).unwrap_or_default();
+        let root = root.map(|(entity, _)| entity);
+        if tile_map.origin != origin || tile_map.root != root {
+            tile_map.origin = origin;
+            tile_map.root = root;
+        }
+    }
+}
+
+/// Puts a tile on the map entity `0`, which has a [`TileMap`] component, instead of the
+/// `TileMap` resource. The tile becomes a child of the map and is despawned with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+#[relationship(relationship_target = MapTiles)]
+pub struct TileOfMap(pub Entity);
+
+/// Tiles of a map entity, see [`TileOfMap`].
+#[derive(Debug, Default, Component)]
+#[relationship_target(relationship = TileOfMap, linked_spawn)]
+pub struct MapTiles(Vec<Entity>);
+
+impl<MARKER: MarkerAble> TileMap<MARKER> {
+    /// Copies the transform of map entities to their [`TileMap::origin`].
+    pub fn sync_entity_origins(
+        mut maps: Query<(Entity, &GlobalTransform, &mut TileMap<MARKER>), Changed<GlobalTransform>>,
+    ) {
+        for (entity, origin, mut tile_map) in maps.iter_mut() {
+            tile_map.origin = *origin;
+            tile_map.root = Some(entity);
+        }
     }
 }
@@ -1,15 +1,21 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy_egui::EguiContexts;
-use egui::Slider;
-use rand::random;
+use bevy_egui::{EguiContext, EguiContexts, PrimaryEguiContext};
+use bevy_inspector_egui::reflect_inspector::ui_for_value;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
 use crate::{
     TileMap,
+    autotile::AutoTiled,
     components::Tile,
     events::{ClearMapEvent, LoadMapEvent, SaveMapEvent},
+    generators::{TerrainBand, TerrainGeneration, TerrainGenerator},
+    map_commands::TileMapCommands,
     marker_traits::{MarkerAble, TileTypeAble},
+    rng::TileMapRng,
+    stats::TileMapStats,
+    tile_data::TileDataRegistry,
     utils,
 };
 
@@ -23,17 +29,28 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileMapEditorPlugin<TYP,
 impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileMapEditorPlugin<TYP, MARKER> {
     fn build(&self, app: &mut App) {
         app.add_observer(add_tile_map_hit_box::<TYP, MARKER>);
+        app.add_observer(remove_tile_map_hit_box::<MARKER>);
         app.insert_resource(TileMapEditor::<TYP, MARKER>::default());
+        app.insert_resource(TileMapStats::<MARKER>::default());
+        app.add_systems(PreStartup, TileHitBoxAssets::<MARKER>::setup);
         app.add_systems(Startup, TileMapEditor::<TYP, MARKER>::setup);
         app.add_systems(
             bevy_egui::EguiPrimaryContextPass,
-            TileMapEditor::<TYP, MARKER>::ui_system,
+            (
+                TileMapEditor::<TYP, MARKER>::ui_system,
+                TileMapEditor::<TYP, MARKER>::generate_ui_system,
+                TileMapEditor::<TYP, MARKER>::inspector_ui_system,
+            ),
         );
         app.add_systems(
             Update,
             (
                 TileMapEditor::<TYP, MARKER>::user_input_system,
+                TileMapEditor::<TYP, MARKER>::hover_system,
                 TileMapEditor::<TYP, MARKER>::update,
+                TileMapEditor::<TYP, MARKER>::preview_system,
+                TileHitBoxAssets::<MARKER>::update,
+                TileMapStats::<MARKER>::update::<TYP>,
             ),
         );
     }
@@ -43,9 +60,20 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileMapEditorPlugin<TYP,
 pub struct TileMapEditor<TYP: TileTypeAble, MARKER: MarkerAble> {
     pub mode: EditorMode,
     pub selected_tile_type: TYP,
+    /// Types to pick from at random with their weights, instead of the selected type.
+    pub variations: Vec<(TYP, f32)>,
     pub random_orientation: bool,
     pub selected_orientation: usize,
+    /// Attached tiles get turned so their +Y points out of the clicked face.
+    pub align_to_surface: bool,
+    /// Placed and painted tiles are marked [`AutoTiled`] and shaped by the auto tiling rules.
+    pub auto_tile: bool,
     pub file_format: utils::FileFormat,
+    /// Tile most recently under the cursor, shown in the inspector panel.
+    pub hovered_tile: Option<Entity>,
+    /// Seed and size (width, height, depth) of the region filled by the "Generate" panel.
+    pub generate_seed: u32,
+    pub generate_size: IVec3,
     show_tile_hit_box: bool,
     _marker: PhantomData<MARKER>,
 }
@@ -62,9 +90,15 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileMapEditor<TYP, MARKE
         Self {
             mode: EditorMode::Paint,
             selected_tile_type: TYP::default(),
+            variations: Vec::new(),
             random_orientation: true,
             selected_orientation: 0,
-            file_format: utils::FileFormat::Csv,
+            align_to_surface: false,
+            auto_tile: false,
+            file_format: utils::FileFormat::default(),
+            hovered_tile: None,
+            generate_seed: 0,
+            generate_size: IVec3::new(32, 8, 32),
             show_tile_hit_box: false,
             _marker: PhantomData,
         }
@@ -73,75 +107,79 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileMapEditor<TYP, MARKE
 impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapEditor<TYP, MARKER> {
     fn user_input_system(
         buttons: Res<ButtonInput<MouseButton>>,
-        windows: Query<&Window>,
-        camera: Query<(&Camera, &GlobalTransform)>,
-        hit_boxes: Query<(&TileHitBox<MARKER>, Entity)>,
-        mut ray_cast: MeshRayCast,
+        cursor: EditorCursor,
         editor: Res<TileMapEditor<TYP, MARKER>>,
-        tile_map: Res<TileMap<MARKER>>,
+        mut map_commands: TileMapCommands<TYP, MARKER>,
+        mut rng: ResMut<TileMapRng<MARKER>>,
         mut commands: Commands,
     ) {
         if !buttons.just_pressed(MouseButton::Left) {
             return;
         }
-        // Create Ray for ray_casting
-        let window = windows.single().unwrap();
-        let Some(curser_pos) = window.cursor_position() else {
-            return;
-        };
-        let (camera, camera_transform) = camera.single().unwrap();
-        let Ok(ray) = camera.viewport_to_world(camera_transform, curser_pos) else {
+        let Some(ray) = cursor.ray() else {
             return;
         };
 
-        // Cast Ray and get tile entity
-        let filter = |e| hit_boxes.get(e).is_ok();
-        let settings = MeshRayCastSettings::default()
-            .with_filter(&filter)
-            .with_visibility(RayCastVisibility::Any);
-        let Some((result, ray_hit)) = ray_cast.cast_ray(ray, &settings).first() else {
+        let tile_map = map_commands.tile_map();
+        // Cast Ray through the grid and get tile entity
+        let Some(hit) = tile_map.raycast(ray, f32::MAX) else {
             return;
         };
-        let tile_entity = hit_boxes.get(*result).unwrap().0.parent;
-        let tile_pos = hit_boxes.get(*result).unwrap().0.map_pos;
+        let tile_pos = hit.map_pos;
 
-        let typ = &editor.selected_tile_type;
+        let typ = rng
+            .choose_weighted(&editor.variations)
+            .unwrap_or(&editor.selected_tile_type);
 
         let orientation = match editor.random_orientation {
-            true => random::<u8>() % (tile_map.config.possible_orientations.len() as u8),
+            true => rng.orientation(tile_map.config.possible_orientations.len()),
             false => editor.selected_orientation as u8,
         };
-        match editor.mode {
+        let placed = match editor.mode {
             EditorMode::Attach => {
-                let map_pos = {
-                    let (_, delta_pos) = tile_map
-                        .config
-                        .next_tile_dir
-                        .iter()
-                        .reduce(|a, b| {
-                            match a.0.angle_between(ray_hit.normal)
-                                < b.0.angle_between(ray_hit.normal)
-                            {
-                                true => a,
-                                false => b,
-                            }
-                        })
-                        .unwrap();
-                    delta_pos + tile_pos
+                // The neighbor on the side that was clicked
+                let map_pos = tile_map.attach_pos(&hit);
+                let orientation = match editor.align_to_surface {
+                    true => tile_map
+                        .orientation_facing(hit.normal, orientation)
+                        .unwrap_or(orientation),
+                    false => orientation,
                 };
-                commands.spawn((Tile::<MARKER>::new(map_pos, orientation), typ.clone()));
+                map_commands
+                    .place(map_pos, typ.clone(), orientation)
+                    .entity()
             }
             EditorMode::Erase => {
-                commands.entity(*result).despawn();
-                commands.entity(tile_entity).despawn_children().despawn();
-            }
-            EditorMode::Paint => {
-                let map_pos = tile_pos;
-                commands.entity(tile_entity).remove::<(TYP, Tile<MARKER>)>();
-                commands
-                    .entity(tile_entity)
-                    .insert((Tile::<MARKER>::new(map_pos, orientation), typ.clone()));
+                // Despawns the hit box and any other children along with the tile
+                map_commands.remove(tile_pos);
+                None
             }
+            EditorMode::Paint => map_commands.replace(tile_pos, typ.clone(), orientation),
+        };
+        if let Some(entity) = placed {
+            match editor.auto_tile {
+                true => commands
+                    .entity(entity)
+                    .insert(AutoTiled::<MARKER>::default()),
+                false => commands.entity(entity).remove::<AutoTiled<MARKER>>(),
+            };
+        }
+    }
+
+    fn hover_system(
+        cursor: EditorCursor,
+        tile_map: Res<TileMap<MARKER>>,
+        mut editor: ResMut<TileMapEditor<TYP, MARKER>>,
+    ) {
+        let Some(ray) = cursor.ray() else {
+            return;
+        };
+        let Some(hit) = tile_map.raycast(ray, f32::MAX) else {
+            return;
+        };
+        let tile_entity = hit.entity;
+        if editor.hovered_tile != Some(tile_entity) {
+            editor.hovered_tile = Some(tile_entity);
         }
     }
 
@@ -164,7 +202,10 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapEditor<TYP, MARKER> {
     pub fn ui_system(
         mut contexts: EguiContexts,
         mut editor_state: ResMut<TileMapEditor<TYP, MARKER>>,
-        tile_map: ResMut<TileMap<MARKER>>,
+        tile_map: Res<TileMap<MARKER>>,
+        stats: Res<TileMapStats<MARKER>>,
+        mut rng: ResMut<TileMapRng<MARKER>>,
+        type_registry: Res<AppTypeRegistry>,
         mut commands: Commands,
     ) {
         let Ok(ctx) = contexts.ctx_mut() else {
@@ -189,40 +230,110 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapEditor<TYP, MARKER> {
 
                 ui.separator();
                 ui.heading("Tile Type");
-                editor_state.selected_tile_type.ui_mut(ui);
+                ui_for_value(
+                    &mut editor_state.selected_tile_type,
+                    ui,
+                    &type_registry.read(),
+                );
+                ui.collapsing("Random Variation", |ui| {
+                    let editor_state = &mut *editor_state;
+                    if ui.button("Add Selected Type").clicked() {
+                        let typ = editor_state.selected_tile_type.clone();
+                        editor_state.variations.push((typ, 1.0));
+                    }
+                    let mut remove = None;
+                    for (index, (typ, weight)) in editor_state.variations.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(utils::type_key(typ));
+                            ui.add(egui::DragValue::new(weight).range(0.0..=100.0).speed(0.1));
+                            if ui.button("x").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        editor_state.variations.remove(index);
+                    }
+                });
+                ui.separator();
+
+                ui.checkbox(&mut editor_state.auto_tile, "Auto Tile")
+                    .on_hover_text("Pick type variant and orientation from the neighbors");
                 ui.separator();
 
                 ui.heading("Orientation");
                 ui.checkbox(&mut editor_state.random_orientation, "Random Orientation");
-                if editor_state.random_orientation == false {
+                ui.checkbox(&mut editor_state.align_to_surface, "Align to Surface")
+                    .on_hover_text("Attach tiles with their top facing out of the clicked face");
+                if editor_state.random_orientation {
+                    let count = tile_map.config.possible_orientations.len();
+                    rng.orientation_weights.resize(count, 1.0);
                     ui.horizontal(|ui| {
-                        ui.label("Orientation (degrees):");
-                        ui.add(Slider::new(
-                            &mut editor_state.selected_orientation,
-                            0..=tile_map.config.possible_orientations.len() - 1,
-                        ));
+                        ui.label("Weights");
+                        for weight in rng.orientation_weights.iter_mut() {
+                            ui.add(egui::DragValue::new(weight).range(0.0..=100.0).speed(0.1));
+                        }
                     });
+                } else {
+                    ui.label(format!(
+                        "Orientation: {}",
+                        editor_state.selected_orientation
+                    ));
+                    for (name, axis) in [("X", Vec3::X), ("Y", Vec3::Y), ("Z", Vec3::Z)] {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Rotate around {}", name));
+                            for (label, steps) in [("-", -1), ("+", 1)] {
+                                if ui.button(label).clicked() {
+                                    let orientation = editor_state.selected_orientation as u8;
+                                    editor_state.selected_orientation =
+                                        tile_map.rotate_orientation(orientation, axis, steps)
+                                            as usize;
+                                }
+                            }
+                        });
+                    }
                 }
 
                 ui.separator();
                 ui.heading("Tile Map Info");
-                ui.label(format!("Tiles placed: {}", tile_map.tiles.len()));
+                ui.label(format!("Tiles placed: {}", stats.tile_count));
                 ui.label(format!("Tile size: {}", tile_map.config.tile_size));
+                match stats.bounds {
+                    Some((min, max)) => ui.label(format!(
+                        "Bounds: {} to {} (size {})",
+                        min,
+                        max,
+                        max - min + 1
+                    )),
+                    None => ui.label("Bounds: empty"),
+                };
+                ui.label(format!("Islands: {}", stats.island_count));
+                ui.collapsing("Tiles per type", |ui| {
+                    for (name, count) in stats.type_counts.iter() {
+                        ui.label(format!("{}: {}", name, count));
+                    }
+                });
+                ui.collapsing("Tiles per orientation", |ui| {
+                    for (orientation, count) in stats.orientation_counts.iter() {
+                        ui.label(format!("{}: {}", orientation, count));
+                    }
+                });
 
                 ui.separator();
                 ui.heading("Save/Load");
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    let mut seed = rng.seed();
+                    if ui.add(egui::DragValue::new(&mut seed)).changed() {
+                        rng.reseed(seed);
+                    }
+                });
                 ui.horizontal(|ui| {
                     ui.label("Format");
-                    ui.selectable_value(
-                        &mut editor_state.file_format,
-                        utils::FileFormat::Csv,
-                        "CSV",
-                    );
-                    ui.selectable_value(
-                        &mut editor_state.file_format,
-                        utils::FileFormat::Json,
-                        "JSON",
-                    );
+                    for format in utils::FileFormat::enabled() {
+                        let label = format.to_string().to_uppercase();
+                        ui.selectable_value(&mut editor_state.file_format, format, label);
+                    }
                 });
                 if ui.button("Save Map").clicked() {
                     commands.trigger(SaveMapEvent::<TYP, MARKER>::new(editor_state.file_format));
@@ -236,43 +347,261 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapEditor<TYP, MARKER> {
                 ui.label("Left Click: Paint | Right Click: Erase");
             });
     }
+
+    /// Generates terrain with the [`TerrainGenerator`] resource, or a single band of the
+    /// selected tile type if there is none.
+    pub fn generate_ui_system(
+        mut contexts: EguiContexts,
+        mut editor_state: ResMut<TileMapEditor<TYP, MARKER>>,
+        tile_map: Res<TileMap<MARKER>>,
+        generator: Option<Res<TerrainGenerator<TYP>>>,
+        mut generation: ResMut<TerrainGeneration<TYP, MARKER>>,
+    ) {
+        let Ok(ctx) = contexts.ctx_mut() else {
+            return;
+        };
+
+        egui::Window::new("Generate")
+            .default_pos([320.0, 10.0])
+            .default_open(false)
+            .show(ctx, |ui| {
+                let editor_state = &mut *editor_state;
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(egui::DragValue::new(&mut editor_state.generate_seed));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    let size = &mut editor_state.generate_size;
+                    ui.add(egui::DragValue::new(&mut size.x).range(1..=512));
+                    ui.add(egui::DragValue::new(&mut size.y).range(1..=128));
+                    ui.add(egui::DragValue::new(&mut size.z).range(1..=512));
+                });
+                if generator.is_none() {
+                    ui.label("No TerrainGenerator resource, using the selected tile type");
+                }
+
+                let mut start = |apply: bool| {
+                    let generator = match &generator {
+                        Some(generator) => (**generator).clone(),
+                        None => TerrainGenerator::new(0)
+                            .with_band(TerrainBand::new(editor_state.selected_tile_type.clone())),
+                    }
+                    .with_seed(editor_state.generate_seed);
+                    let size = editor_state.generate_size;
+                    let min = IVec3::new(-size.x / 2, 0, -size.z / 2);
+                    let max = min + size - IVec3::ONE;
+                    let task = generator.spawn_task(&tile_map.config, min, max);
+                    generation.start(task, apply);
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("Preview").clicked() {
+                        start(false);
+                    }
+                    if ui.button("Generate").clicked() {
+                        start(true);
+                    }
+                });
+                if generation.is_running() {
+                    ui.label("Generating...");
+                } else if !generation.preview.is_empty() {
+                    ui.label(format!("Preview: {} tiles", generation.preview.len()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply Preview").clicked() {
+                            generation.apply();
+                        }
+                        if ui.button("Discard Preview").clicked() {
+                            generation.preview.clear();
+                        }
+                    });
+                }
+            });
+    }
+
+    /// Outlines the cells of the terrain preview.
+    fn preview_system(
+        generation: Res<TerrainGeneration<TYP, MARKER>>,
+        tile_map: Res<TileMap<MARKER>>,
+        mut gizmos: Gizmos,
+    ) {
+        let size = tile_map.config.tile_size;
+        for record in generation.preview.iter() {
+            let transform = tile_map.origin.mul_transform(
+                Transform::from_translation(tile_map.config.grid_to_world(record.map_pos))
+                    .with_scale(size),
+            );
+            gizmos.cube(transform, Color::linear_rgb(0.2, 0.8, 1.0));
+        }
+    }
+
+    pub fn inspector_ui_system(world: &mut World) {
+        let Ok(egui_context) = world
+            .query_filtered::<&mut EguiContext, With<PrimaryEguiContext>>()
+            .single(world)
+        else {
+            return;
+        };
+        let mut egui_context = egui_context.clone();
+        let hovered_tile = world.resource::<Self>().hovered_tile;
+
+        egui::Window::new("Tile Inspector")
+            .default_pos([10.0, 500.0])
+            .show(egui_context.get_mut(), |ui| {
+                let Some(entity) = hovered_tile.filter(|e| world.get_entity(*e).is_ok()) else {
+                    ui.label("Hover a tile to inspect it");
+                    return;
+                };
+                let tile_ref = world.entity(entity);
+                ui.label(format!("Entity: {}", entity));
+                if let Some(tile) = tile_ref.get::<Tile<MARKER>>() {
+                    ui.label(format!("Position: {}", tile.map_pos));
+                    ui.label(format!("Orientation: {}", tile.orientation));
+                }
+                if let Some(typ) = tile_ref.get::<TYP>() {
+                    let typ = serde_json::to_string(typ).unwrap_or_default();
+                    ui.label(format!("Type: {}", typ));
+                }
+                ui.separator();
+                if !world
+                    .resource::<TileDataRegistry<MARKER>>()
+                    .entries
+                    .is_empty()
+                {
+                    ui.heading("Tile Data");
+                    world.resource_scope(|world, tile_data: Mut<TileDataRegistry<MARKER>>| {
+                        tile_data.edit_ui(world, entity, ui);
+                    });
+                    ui.separator();
+                }
+                ui.collapsing("Components", |ui| {
+                    bevy_inspector_egui::bevy_inspector::ui_for_entity(world, entity, ui);
+                });
+            });
+    }
+}
+
+/// Ray from the primary camera through the cursor.
+#[derive(SystemParam)]
+struct EditorCursor<'w, 's> {
+    windows: Query<'w, 's, &'static Window>,
+    camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+impl EditorCursor<'_, '_> {
+    fn ray(&self) -> Option<Ray3d> {
+        let window = self.windows.single().ok()?;
+        let curser_pos = window.cursor_position()?;
+        let (camera, camera_transform) = self.camera.single().ok()?;
+        camera.viewport_to_world(camera_transform, curser_pos).ok()
+    }
 }
 
+/// Box around a tile, only drawn when "Show Tile Box" is enabled. Picking goes through
+/// [`TileMap::raycast`] and doesn't need it.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, Component)]
 pub struct TileHitBox<MARKER: MarkerAble> {
     pub parent: Entity,
     pub map_pos: IVec3,
     _marker: PhantomData<MARKER>,
 }
+
+/// Mesh and material shared by all hit boxes of a map.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct TileHitBoxAssets<MARKER: MarkerAble> {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    size: Vec3,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> TileHitBoxAssets<MARKER> {
+    fn setup(
+        tile_map: Res<TileMap<MARKER>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut commands: Commands,
+    ) {
+        let size = tile_map.config.tile_size;
+        commands.insert_resource(Self {
+            mesh: meshes.add(Cuboid::from_size(size)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::linear_rgba(1.0, 0.0, 0.0, 0.9),
+                alpha_mode: AlphaMode::Add,
+                ..Default::default()
+            }),
+            size,
+            _marker: PhantomData,
+        });
+    }
+    /// Rebuilds the shared mesh in place when the tile size changed.
+    fn update(
+        tile_map: Res<TileMap<MARKER>>,
+        hit_box_assets: Option<ResMut<Self>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        let Some(mut hit_box_assets) = hit_box_assets else {
+            return;
+        };
+        if !tile_map.is_changed() || hit_box_assets.size == tile_map.config.tile_size {
+            return;
+        }
+        hit_box_assets.size = tile_map.config.tile_size;
+        let _ = meshes.insert(
+            &hit_box_assets.mesh,
+            Cuboid::from_size(hit_box_assets.size).into(),
+        );
+    }
+}
+
 fn add_tile_map_hit_box<TYP: TileTypeAble, MARKER: MarkerAble>(
     event: On<Add, Tile<MARKER>>,
     tile_map: Res<TileMap<MARKER>>,
     tiles: Query<&Tile<MARKER>>,
     editor: Res<TileMapEditor<TYP, MARKER>>,
+    hit_box_assets: Res<TileHitBoxAssets<MARKER>>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     let entity = event.entity;
     let tile = tiles.get(entity).unwrap();
 
-    let transform = Transform::from_translation(tile.map_pos.as_vec3() * tile_map.config.tile_size);
+    // The hit box is placed relative to the tile, so the tile needs a transform of its own
+    let transform = tile_map.tile_transform(tile);
+    commands
+        .entity(entity)
+        .insert_if_new((transform, Visibility::default()));
+
     let hit_box = TileHitBox::<MARKER> {
-        map_pos: tile.map_pos.clone(),
+        map_pos: tile.map_pos,
         parent: entity,
         _marker: PhantomData,
     };
-    let mesh = Mesh3d(meshes.add(Cuboid::from_size(tile_map.config.tile_size)));
-    let material = MeshMaterial3d(materials.add(StandardMaterial {
-        base_color: Color::linear_rgba(1.0, 0.0, 0.0, 0.9),
-        alpha_mode: AlphaMode::Add,
-        ..Default::default()
-    }));
+    let mesh = Mesh3d(hit_box_assets.mesh.clone());
+    let material = MeshMaterial3d(hit_box_assets.material.clone());
 
     let visibility = match editor.show_tile_hit_box {
         true => Visibility::Visible,
         false => Visibility::Hidden,
     };
 
-    let _ = commands.spawn((hit_box, mesh, material, transform, visibility));
+    commands.spawn((
+        hit_box,
+        mesh,
+        material,
+        // Keeps the hit box aligned with the grid cell whatever the tile orientation
+        Transform::from_rotation(transform.rotation.inverse()),
+        visibility,
+        ChildOf(entity),
+    ));
+}
+fn remove_tile_map_hit_box<MARKER: MarkerAble>(
+    event: On<Remove, Tile<MARKER>>,
+    children: Query<&Children>,
+    hit_boxes: Query<(), With<TileHitBox<MARKER>>>,
+    mut commands: Commands,
+) {
+    let Ok(children) = children.get(event.entity) else {
+        return;
+    };
+    children
+        .iter()
+        .filter(|child| hit_boxes.contains(*child))
+        .for_each(|child| commands.entity(child).try_despawn());
 }
@@ -1,199 +1,211 @@
 use bevy::prelude::*;
-use serde_json::Value;
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs;
 use std::marker::PhantomData;
 use std::path::Path;
 
-use crate::components::Tile;
-use crate::editor::TileHitBox;
+use crate::components::{Tile, TileOfMap};
+use crate::io::{self, TileRecord};
 use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::rng::TileMapRng;
+use crate::streaming::TileMapStreaming;
+use crate::tile_data::TileDataRegistry;
 use crate::{TileMap, utils};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
 pub struct LoadMapEvent<TYP: TileTypeAble, MARKER: MarkerAble> {
     phantom_data: PhantomData<(TYP, MARKER)>,
     format: utils::FileFormat,
+    map: Option<Entity>,
 }
 impl<TYP: TileTypeAble, MARKER: MarkerAble> LoadMapEvent<TYP, MARKER> {
     pub fn new(format: utils::FileFormat) -> Self {
         Self {
             phantom_data: PhantomData,
             format,
+            map: None,
         }
     }
-    pub fn on(event: On<Self>, tile_map: Res<TileMap<MARKER>>, commands: Commands) {
-        match event.format {
-            utils::FileFormat::Json => Self::load_json(tile_map, commands),
-            utils::FileFormat::Csv => Self::load_csv(tile_map, commands),
-        };
+    /// Load into the map entity `map` instead of the `TileMap` resource.
+    pub fn for_map(mut self, map: Entity) -> Self {
+        self.map = Some(map);
+        self
     }
-    fn load_json(tile_map: Res<TileMap<MARKER>>, mut commands: Commands) {
-        let path = format!("{}.json", tile_map.config.source_path);
-        let path = Path::new(&path);
-        let Ok(file) = File::open(path) else {
-            println!(
-                "couldn't load tiles for tile_map, since file {:?} doesn't exist",
-                path
-            );
-            return;
-        };
-
-        let Ok(values): Result<Value, _> = serde_json::from_reader(file) else {
-            println!("Tile Map source files contains invalid Json ({:?})", path);
+    pub fn on(
+        event: On<Self>,
+        tile_map: Res<TileMap<MARKER>>,
+        maps: Query<&TileMap<MARKER>>,
+        mut rng: ResMut<TileMapRng<MARKER>>,
+        tile_data: Res<TileDataRegistry<MARKER>>,
+        mut commands: Commands,
+    ) {
+        let Some(tile_map) = target_map(&tile_map, &maps, event.map) else {
             return;
         };
-        let Some(Value::Array(tiles)) = values.get("tiles") else {
-            return;
+        let path = io::map_path(&tile_map.config.source_path, event.format);
+        let map = match io::read_map_from_file::<TYP>(&path, event.format) {
+            Ok(map) => map,
+            Err(e) => {
+                println!("couldn't load tiles for tile_map, since {}", e);
+                return;
+            }
         };
-
-        tiles.iter().for_each(|tile| {
-            let pos: Option<IVec3> = tile
-                .get("pos")
-                .map(Clone::clone)
-                .and_then(|v| serde_json::from_value(v).ok());
-            let orientation: Option<u8> = tile
-                .get("dir")
-                .map(Clone::clone)
-                .and_then(|v| serde_json::from_value(v).ok());
-            let typ: Option<TYP> = tile
-                .get("typ")
-                .map(Clone::clone)
-                .and_then(|v| serde_json::from_value(v).ok());
-            if let (Some(map_pos), Some(orientation), Some(typ)) = (pos, orientation, typ) {
-                commands.spawn((Tile::<MARKER>::new(map_pos, orientation), typ));
-            };
-        });
+        if let Some(seed) = map.seed {
+            rng.reseed(seed);
+        }
+        spawn_tiles(&mut commands, tile_map, event.map, &tile_data, map.tiles);
     }
-    fn load_csv(tile_map: Res<TileMap<MARKER>>, mut commands: Commands) {
-        let path = format!("{}.csv", tile_map.config.source_path);
-        let path = Path::new(&path);
+}
 
-        let Ok(file) = File::open(path) else {
-            println!(
-                "couldn't load tiles for tile_map, since file {:?} doesn't exist",
-                path
-            );
-            return;
+/// Spawns loaded tiles into `tile_map`, despawning the tiles already in their cells the way
+/// [`OccupiedPolicy::Replace`](crate::map_commands::OccupiedPolicy::Replace) does, so
+/// [`TileRemoved`] and [`TilePlaced`] fire for them.
+pub(crate) fn spawn_tiles<TYP: TileTypeAble, MARKER: MarkerAble>(
+    commands: &mut Commands,
+    tile_map: &TileMap<MARKER>,
+    map: Option<Entity>,
+    tile_data: &TileDataRegistry<MARKER>,
+    tiles: Vec<TileRecord<TYP>>,
+) {
+    // Later tiles in the same cell replace earlier ones of the same batch
+    let mut spawned: HashMap<IVec3, Entity> = HashMap::new();
+    tiles.into_iter().for_each(|tile| {
+        let occupant = spawned
+            .get(&tile.map_pos)
+            .or(tile_map.tiles.get(&tile.map_pos));
+        if let Some(occupant) = occupant {
+            commands.entity(*occupant).despawn();
+        }
+        let bundle = (
+            Tile::<MARKER>::new(tile.map_pos, tile.orientation),
+            tile.typ,
+        );
+        // Spawned together, so the index observers see the map entity on their first run
+        let mut entity = match map {
+            Some(map) => commands.spawn((bundle, TileOfMap(map))),
+            None => commands.spawn(bundle),
         };
+        tile_data.load(&mut entity, tile.data);
+        spawned.insert(tile.map_pos, entity.id());
+    });
+}
 
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(true)
-            .from_reader(file);
-
-        for result in reader.records() {
-            let Ok(record) = result else { continue };
-
-            let Ok(x) = record[0].parse::<i32>() else {
-                continue;
-            };
-            let Ok(y) = record[1].parse::<i32>() else {
-                continue;
-            };
-            let Ok(z) = record[2].parse::<i32>() else {
-                continue;
-            };
-            let Ok(dir) = record[3].parse::<u8>() else {
-                continue;
-            };
-
-            let typ: Result<TYP, _> = serde_json::from_str(&record[4]);
-            let Ok(typ) = typ else { continue };
-
-            let map_pos = IVec3::new(x, y, z);
-
-            commands.spawn((Tile::<MARKER>::new(map_pos, dir), typ));
-        }
+/// The map entity `map` if given, the `TileMap` resource otherwise.
+fn target_map<'a, MARKER: MarkerAble>(
+    tile_map: &'a TileMap<MARKER>,
+    maps: &'a Query<&TileMap<MARKER>>,
+    map: Option<Entity>,
+) -> Option<&'a TileMap<MARKER>> {
+    let Some(map) = map else {
+        return Some(tile_map);
+    };
+    let found = maps.get(map).ok();
+    if found.is_none() {
+        println!("entity {} has no TileMap component", map);
     }
+    found
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
 pub struct SaveMapEvent<TYP: TileTypeAble, MARKER: MarkerAble> {
     phantom_data: PhantomData<(TYP, MARKER)>,
     format: utils::FileFormat,
+    chunked: bool,
+    map: Option<Entity>,
 }
 impl<TYP: TileTypeAble, MARKER: MarkerAble> SaveMapEvent<TYP, MARKER> {
     pub fn new(format: utils::FileFormat) -> Self {
         Self {
             phantom_data: PhantomData,
             format,
+            chunked: false,
+            map: None,
         }
     }
+    /// Save the map entity `map` instead of the `TileMap` resource.
+    pub fn for_map(mut self, map: Entity) -> Self {
+        self.map = Some(map);
+        self
+    }
+    /// Save every chunk into its own file inside the `source_path` directory instead of a
+    /// single file, as read by the [`TileMapStreamer`](crate::streaming::TileMapStreamer).
+    /// Chunk files are written in the config's `file_format`, the one the streamer reads,
+    /// whatever format the event was created with.
+    pub fn chunked(mut self) -> Self {
+        self.chunked = true;
+        self
+    }
     pub fn on(
         event: On<Self>,
         tile_map: Res<TileMap<MARKER>>,
-        tiles: Query<(&TYP, &Tile<MARKER>)>,
+        maps: Query<&TileMap<MARKER>>,
+        rng: Res<TileMapRng<MARKER>>,
+        tile_data: Res<TileDataRegistry<MARKER>>,
+        tiles: Query<(EntityRef, &TYP, &Tile<MARKER>, Option<&TileOfMap>)>,
+        streaming: Res<TileMapStreaming<TYP, MARKER>>,
     ) {
-        match event.format {
-            utils::FileFormat::Json => Self::save_json(tile_map, tiles),
-            utils::FileFormat::Csv => Self::save_csv(tile_map, tiles),
-        };
-    }
-    fn save_json(tile_map: Res<TileMap<MARKER>>, tiles: Query<(&TYP, &Tile<MARKER>)>) {
-        let path = format!("{}.json", tile_map.config.source_path);
-        let path = Path::new(&path);
-        let Ok(mut file) = File::create(path) else {
-            println!(
-                "{} \n{} {:?}",
-                "couldn't create storage file for tile_map,",
-                "since full directory path does not exist for path: {:?}",
-                path
-            );
+        let Some(tile_map) = target_map(&tile_map, &maps, event.map) else {
             return;
         };
-        let tiles_json: Vec<Value> = tiles
+        // Only the resource map is streamed
+        let streaming = (event.map.is_none() && streaming.is_streaming()).then_some(&*streaming);
+        let records: Vec<TileRecord<&TYP>> = tiles
             .iter()
-            .map(|(typ, tile)| {
-                let pos = tile.map_pos;
-                let dir = tile.orientation;
-                serde_json::json!({ "pos": pos, "dir": dir, "typ": typ })
+            .filter(|(_, _, _, tile_of)| tile_of.map(|tile_of| tile_of.0) == event.map)
+            .map(|(entity, typ, tile, _)| {
+                TileRecord::new(tile.map_pos, tile.orientation, typ)
+                    .with_data(tile_data.save(&entity))
             })
             .collect();
-        let value = serde_json::json!({ "tiles": tiles_json });
-        match serde_json::to_writer_pretty(&mut file, &value) {
+        let result = match event.chunked {
+            true => {
+                let format = tile_map.config.file_format;
+                Self::save_chunks(tile_map, format, records, streaming)
+            }
+            false => {
+                let path = io::map_path(&tile_map.config.source_path, event.format);
+                io::write_map_to_file(&path, event.format, Some(rng.seed()), &records)
+            }
+        };
+        match result {
             Ok(_) => println!("Tile Map saved successfully"),
             Err(e) => println!("Failed to save the Tile Map: {}", e),
         }
     }
-    fn save_csv(tile_map: Res<TileMap<MARKER>>, tiles: Query<(&TYP, &Tile<MARKER>)>) {
-        let path = format!("{}.csv", tile_map.config.source_path);
-        let path = Path::new(&path);
-
-        let Ok(file) = File::create(path) else {
-            println!(
-                "couldn't create storage file for tile_map, directory may not exist: {:?}",
-                path
-            );
-            return;
-        };
-
-        let mut writer = csv::WriterBuilder::new()
-            .has_headers(true)
-            .from_writer(file);
-
-        // Write header
-        let _ = writer.write_record(["x", "y", "z", "dir", "typ"]);
+    fn save_chunks(
+        tile_map: &TileMap<MARKER>,
+        format: utils::FileFormat,
+        records: Vec<TileRecord<&TYP>>,
+        streaming: Option<&TileMapStreaming<TYP, MARKER>>,
+    ) -> Result<(), String> {
+        let dir = Path::new(&tile_map.config.source_path);
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
 
-        for (typ, tile) in tiles.iter() {
-            let pos = tile.map_pos;
-            let dir = tile.orientation;
-
-            let Ok(typ_json) = serde_json::to_string(typ) else {
-                continue;
-            };
+        let mut chunks: HashMap<IVec3, Vec<TileRecord<&TYP>>> = HashMap::new();
+        records.into_iter().for_each(|record| {
+            let chunk = tile_map.tiles.chunk_of(record.map_pos);
+            chunks.entry(chunk).or_default().push(record);
+        });
 
-            let _ = writer.write_record(&[
-                pos.x.to_string(),
-                pos.y.to_string(),
-                pos.z.to_string(),
-                dir.to_string(),
-                typ_json,
-            ]);
+        // Remove chunk files of chunks that are empty by now. While streaming, chunks that
+        // aren't loaded have no tiles in memory, so their files are kept.
+        let emptied = |chunk: &IVec3| {
+            !chunks.contains_key(chunk) && streaming.is_none_or(|s| s.is_loaded(*chunk))
+        };
+        if let Ok(entries) = fs::read_dir(dir) {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| io::parse_chunk_file_name(path, format).is_some_and(|c| emptied(&c)))
+                .for_each(|path| {
+                    let _ = fs::remove_file(path);
+                });
         }
 
-        match writer.flush() {
-            Ok(_) => println!("Tile Map saved successfully"),
-            Err(e) => println!("Failed to save the Tile Map: {}", e),
-        }
+        chunks.iter().try_for_each(|(chunk, records)| {
+            let path = io::chunk_path(&tile_map.config.source_path, *chunk, format);
+            io::write_tiles_to_file(&path, format, records)
+        })
     }
 }
 
@@ -201,33 +213,154 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> SaveMapEvent<TYP, MARKER> {
 pub struct ClearMapEvent<TYP: TileTypeAble, MARKER: MarkerAble> {
     _phantom: PhantomData<(TYP, MARKER)>,
     create_source_tile: bool,
+    map: Option<Entity>,
 }
-impl<TYP: TileTypeAble, MARKER: MarkerAble> ClearMapEvent<TYP, MARKER> {
-    pub fn new() -> Self {
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for ClearMapEvent<TYP, MARKER> {
+    fn default() -> Self {
         Self {
             _phantom: PhantomData,
             create_source_tile: false,
+            map: None,
         }
     }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> ClearMapEvent<TYP, MARKER> {
+    pub fn new() -> Self {
+        Self::default()
+    }
     pub fn with_source_tile(mut self) -> Self {
         self.create_source_tile = true;
         self
     }
+    /// Clear the map entity `map` instead of the `TileMap` resource.
+    pub fn for_map(mut self, map: Entity) -> Self {
+        self.map = Some(map);
+        self
+    }
     pub fn on(
         event: On<Self>,
-        tiles: Query<(Entity, &Tile<MARKER>)>,
-        editor_hit_boxes: Query<(Entity, &TileHitBox<MARKER>)>,
+        tiles: Query<(Entity, &Tile<MARKER>, Option<&TileOfMap>)>,
         mut commands: Commands,
     ) {
         tiles
             .iter()
-            .for_each(|(e, _t)| commands.entity(e).despawn());
-        editor_hit_boxes
-            .iter()
-            .for_each(|(e, _t)| commands.entity(e).despawn());
+            .filter(|(_, _, tile_of)| tile_of.map(|tile_of| tile_of.0) == event.map)
+            .for_each(|(e, _t, _)| commands.entity(e).despawn());
 
         if event.create_source_tile {
-            commands.spawn((Tile::<MARKER>::new(IVec3::ZERO, 0), TYP::default()));
+            let bundle = (Tile::<MARKER>::new(IVec3::ZERO, 0), TYP::default());
+            match event.map {
+                Some(map) => commands.spawn((bundle, TileOfMap(map))),
+                None => commands.spawn(bundle),
+            };
+        }
+    }
+}
+
+/// A tile was spawned, by the editor, a loader or any other code.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub struct TilePlaced<TYP: TileTypeAble, MARKER: MarkerAble> {
+    pub entity: Entity,
+    pub map_pos: IVec3,
+    pub orientation: u8,
+    pub typ: TYP,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TilePlaced<TYP, MARKER> {
+    pub fn new(entity: Entity, map_pos: IVec3, orientation: u8, typ: TYP) -> Self {
+        Self {
+            entity,
+            map_pos,
+            orientation,
+            typ,
+            _marker: PhantomData,
+        }
+    }
+    pub fn on_tile_added(
+        event: On<Add, Tile<MARKER>>,
+        tiles: Query<(&Tile<MARKER>, &TYP)>,
+        mut commands: Commands,
+    ) {
+        let Ok((tile, typ)) = tiles.get(event.entity) else {
+            return;
+        };
+        commands.trigger(Self::new(
+            event.entity,
+            tile.map_pos,
+            tile.orientation,
+            typ.clone(),
+        ));
+    }
+}
+
+/// A tile was despawned or lost its `Tile` component. Holds the values it had before.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub struct TileRemoved<TYP: TileTypeAble, MARKER: MarkerAble> {
+    pub entity: Entity,
+    pub map_pos: IVec3,
+    pub orientation: u8,
+    pub typ: TYP,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileRemoved<TYP, MARKER> {
+    pub fn new(entity: Entity, map_pos: IVec3, orientation: u8, typ: TYP) -> Self {
+        Self {
+            entity,
+            map_pos,
+            orientation,
+            typ,
+            _marker: PhantomData,
+        }
+    }
+    pub fn on_tile_removed(
+        event: On<Remove, Tile<MARKER>>,
+        tiles: Query<(&Tile<MARKER>, &TYP)>,
+        mut commands: Commands,
+    ) {
+        let Ok((tile, typ)) = tiles.get(event.entity) else {
+            return;
+        };
+        commands.trigger(Self::new(
+            event.entity,
+            tile.map_pos,
+            tile.orientation,
+            typ.clone(),
+        ));
+    }
+}
+
+/// The type or orientation of an existing tile was changed.
+///
+/// Only sent by [`TileMapCommands::replace`](crate::map_commands::TileMapCommands::replace), and
+/// so by the editor's paint mode and auto-tiling. Inserting a new `TYP` or `Tile` on an existing
+/// tile directly doesn't send it.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub struct TileChanged<TYP: TileTypeAble, MARKER: MarkerAble> {
+    pub entity: Entity,
+    pub map_pos: IVec3,
+    pub old_orientation: u8,
+    pub new_orientation: u8,
+    pub old_typ: TYP,
+    pub new_typ: TYP,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileChanged<TYP, MARKER> {
+    pub fn new(
+        entity: Entity,
+        map_pos: IVec3,
+        old_orientation: u8,
+        new_orientation: u8,
+        old_typ: TYP,
+        new_typ: TYP,
+    ) -> Self {
+        Self {
+            entity,
+            map_pos,
+            old_orientation,
+            new_orientation,
+            old_typ,
+            new_typ,
+            _marker: PhantomData,
         }
     }
 }
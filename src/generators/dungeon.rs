@@ -0,0 +1,438 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::collections::VecDeque;
+
+use crate::io::TileRecord;
+use crate::marker_traits::TileTypeAble;
+
+const SIDES: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// Tile types a [`FloorPlan`] is built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelTiles<TYP: TileTypeAble> {
+    pub floor: TYP,
+    /// Placed around the floor, `wall_height` tiles high.
+    pub wall: Option<TYP>,
+    pub wall_height: i32,
+}
+impl<TYP: TileTypeAble> LevelTiles<TYP> {
+    pub fn new(floor: TYP) -> Self {
+        Self {
+            floor,
+            wall: None,
+            wall_height: 1,
+        }
+    }
+    pub fn with_walls(mut self, wall: TYP, wall_height: i32) -> Self {
+        self.wall = Some(wall);
+        self.wall_height = wall_height.max(1);
+        self
+    }
+}
+
+/// Which cells of a flat level are floor. `x` of the plan is map `x`, `y` of the plan is map `z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FloorPlan {
+    pub size: IVec2,
+    cells: Vec<bool>,
+}
+impl FloorPlan {
+    pub fn new(size: IVec2) -> Self {
+        let size = size.max(IVec2::ONE);
+        Self {
+            size,
+            cells: vec![false; (size.x * size.y) as usize],
+        }
+    }
+    pub fn is_floor(&self, pos: IVec2) -> bool {
+        self.index(pos).is_some_and(|index| self.cells[index])
+    }
+    pub fn set_floor(&mut self, pos: IVec2, floor: bool) {
+        if let Some(index) = self.index(pos) {
+            self.cells[index] = floor;
+        }
+    }
+    pub fn floor_count(&self) -> usize {
+        self.cells.iter().filter(|floor| **floor).count()
+    }
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> + use<> {
+        let size = self.size;
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    /// Floor tiles at `origin.y`, with walls on every other cell touching the floor.
+    pub fn to_records<TYP: TileTypeAble>(
+        &self,
+        origin: IVec3,
+        tiles: &LevelTiles<TYP>,
+    ) -> Vec<TileRecord<TYP>> {
+        let map_pos = |pos: IVec2, y: i32| origin + IVec3::new(pos.x, y, pos.y);
+        let mut records = Vec::new();
+        for pos in self.positions() {
+            if self.is_floor(pos) {
+                records.push(TileRecord::new(map_pos(pos, 0), 0, tiles.floor.clone()));
+                continue;
+            }
+            let Some(wall) = &tiles.wall else {
+                continue;
+            };
+            let touches_floor = (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| IVec2::new(x, y)))
+                .any(|offset| self.is_floor(pos + offset));
+            if touches_floor {
+                records.extend(
+                    (0..tiles.wall_height)
+                        .map(|y| TileRecord::new(map_pos(pos, y), 0, wall.clone())),
+                );
+            }
+        }
+        records
+    }
+
+    /// Turns every floor region except the largest one back into wall.
+    pub fn keep_largest_region(&mut self) {
+        let mut region = vec![usize::MAX; self.cells.len()];
+        let mut sizes = Vec::new();
+        for start in self.positions() {
+            let start_index = self.index(start).unwrap();
+            if !self.cells[start_index] || region[start_index] != usize::MAX {
+                continue;
+            }
+            let id = sizes.len();
+            let mut size = 0;
+            let mut queue = VecDeque::from([start]);
+            region[start_index] = id;
+            while let Some(pos) = queue.pop_front() {
+                size += 1;
+                for side in SIDES {
+                    let next = pos + side;
+                    if let Some(index) = self.index(next)
+                        && self.cells[index]
+                        && region[index] == usize::MAX
+                    {
+                        region[index] = id;
+                        queue.push_back(next);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        let Some(largest) = (0..sizes.len()).max_by_key(|id| sizes[*id]) else {
+            return;
+        };
+        for (cell, id) in self.cells.iter_mut().zip(region) {
+            *cell = *cell && id == largest;
+        }
+    }
+
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        if pos.cmplt(IVec2::ZERO).any() || pos.cmpge(self.size).any() {
+            return None;
+        }
+        Some((pos.x + pos.y * self.size.x) as usize)
+    }
+    /// Whether `pos` lies inside the plan without touching its border.
+    fn is_inner(&self, pos: IVec2) -> bool {
+        pos.cmpge(IVec2::ONE).all() && pos.cmplt(self.size - 1).all()
+    }
+}
+
+/// Rooms in the leaves of a binary space partition, joined by corridors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BspDungeon {
+    pub seed: u64,
+    pub size: IVec2,
+    /// Smallest room side length.
+    pub min_room: i32,
+    /// How often the space may be split.
+    pub max_depth: u32,
+}
+impl BspDungeon {
+    pub fn new(seed: u64, size: IVec2) -> Self {
+        Self {
+            seed,
+            size,
+            min_room: 4,
+            max_depth: 5,
+        }
+    }
+    pub fn with_min_room(mut self, min_room: i32) -> Self {
+        self.min_room = min_room.max(1);
+        self
+    }
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn plan(&self) -> FloorPlan {
+        let mut plan = FloorPlan::new(self.size);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let size = plan.size;
+        self.split(&mut plan, &mut rng, IVec2::ZERO, size, 0);
+        plan
+    }
+    pub fn generate<TYP: TileTypeAble>(
+        &self,
+        origin: IVec3,
+        tiles: &LevelTiles<TYP>,
+    ) -> Vec<TileRecord<TYP>> {
+        self.plan().to_records(origin, tiles)
+    }
+
+    /// Fills the area from `min` with `size` and returns the center of one of its rooms.
+    fn split(
+        &self,
+        plan: &mut FloorPlan,
+        rng: &mut StdRng,
+        min: IVec2,
+        size: IVec2,
+        depth: u32,
+    ) -> IVec2 {
+        // Rooms keep a wall of one cell to the border of their area
+        let smallest = self.min_room + 2;
+        let can_split = IVec2::new(
+            (size.x >= smallest * 2) as i32,
+            (size.y >= smallest * 2) as i32,
+        );
+        if depth >= self.max_depth || can_split == IVec2::ZERO {
+            return self.room(plan, rng, min, size);
+        }
+        let axis = match (can_split.x, can_split.y) {
+            (1, 1) => match size.x == size.y {
+                true => rng.random_range(0..2),
+                false => (size.y > size.x) as usize,
+            },
+            (1, 0) => 0,
+            _ => 1,
+        };
+        let at = rng.random_range(smallest..=size[axis] - smallest);
+        let mut first_size = size;
+        first_size[axis] = at;
+        let mut second_min = min;
+        second_min[axis] += at;
+        let mut second_size = size;
+        second_size[axis] -= at;
+
+        let a = self.split(plan, rng, min, first_size, depth + 1);
+        let b = self.split(plan, rng, second_min, second_size, depth + 1);
+        carve_corridor(plan, rng, a, b);
+        match rng.random_range(0..2) {
+            0 => a,
+            _ => b,
+        }
+    }
+    fn room(&self, plan: &mut FloorPlan, rng: &mut StdRng, min: IVec2, size: IVec2) -> IVec2 {
+        let inner = (size - 2).max(IVec2::ONE);
+        let room_size = IVec2::new(
+            rng.random_range(self.min_room.min(inner.x)..=inner.x),
+            rng.random_range(self.min_room.min(inner.y)..=inner.y),
+        );
+        let room_min = min
+            + 1
+            + IVec2::new(
+                rng.random_range(0..=inner.x - room_size.x),
+                rng.random_range(0..=inner.y - room_size.y),
+            );
+        for y in 0..room_size.y {
+            for x in 0..room_size.x {
+                plan.set_floor(room_min + IVec2::new(x, y), true);
+            }
+        }
+        room_min + room_size / 2
+    }
+}
+
+/// L shaped corridor between `a` and `b`.
+fn carve_corridor(plan: &mut FloorPlan, rng: &mut StdRng, a: IVec2, b: IVec2) {
+    let corner = match rng.random_range(0..2) {
+        0 => IVec2::new(b.x, a.y),
+        _ => IVec2::new(a.x, b.y),
+    };
+    for (from, to) in [(a, corner), (corner, b)] {
+        let step = (to - from).signum();
+        let mut pos = from;
+        plan.set_floor(pos, true);
+        while pos != to {
+            pos += step;
+            plan.set_floor(pos, true);
+        }
+    }
+}
+
+/// Caves dug by a random walk from the center.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrunkardWalk {
+    pub seed: u64,
+    pub size: IVec2,
+    /// Share of the inner cells to dig out.
+    pub floor_ratio: f32,
+    /// Gives up after this many steps.
+    pub max_steps: usize,
+}
+impl DrunkardWalk {
+    pub fn new(seed: u64, size: IVec2) -> Self {
+        Self {
+            seed,
+            size,
+            floor_ratio: 0.4,
+            max_steps: 100_000,
+        }
+    }
+    pub fn with_floor_ratio(mut self, floor_ratio: f32) -> Self {
+        self.floor_ratio = floor_ratio.clamp(0.0, 1.0);
+        self
+    }
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn plan(&self) -> FloorPlan {
+        let mut plan = FloorPlan::new(self.size);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let inner = (plan.size - 2).max(IVec2::ZERO);
+        let target = (self.floor_ratio * (inner.x * inner.y) as f32) as usize;
+        let mut pos = plan.size / 2;
+        let mut floor_count = 0;
+        for _ in 0..self.max_steps {
+            if floor_count >= target {
+                break;
+            }
+            if plan.is_inner(pos) && !plan.is_floor(pos) {
+                plan.set_floor(pos, true);
+                floor_count += 1;
+            }
+            let next = pos + SIDES[rng.random_range(0..SIDES.len())];
+            if plan.is_inner(next) {
+                pos = next;
+            }
+        }
+        plan
+    }
+    pub fn generate<TYP: TileTypeAble>(
+        &self,
+        origin: IVec3,
+        tiles: &LevelTiles<TYP>,
+    ) -> Vec<TileRecord<TYP>> {
+        self.plan().to_records(origin, tiles)
+    }
+}
+
+/// Caves from random noise smoothed by a cellular automaton.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaveAutomaton {
+    pub seed: u64,
+    pub size: IVec2,
+    /// Chance of a cell to start as wall.
+    pub wall_chance: f32,
+    pub iterations: u32,
+    /// A cell becomes wall with more than this many walls among its 8 neighbors, and floor
+    /// with fewer.
+    pub threshold: u32,
+    /// Drop caves not connected to the largest one.
+    pub connected: bool,
+}
+impl CaveAutomaton {
+    pub fn new(seed: u64, size: IVec2) -> Self {
+        Self {
+            seed,
+            size,
+            wall_chance: 0.45,
+            iterations: 5,
+            threshold: 4,
+            connected: true,
+        }
+    }
+    pub fn with_wall_chance(mut self, wall_chance: f32) -> Self {
+        self.wall_chance = wall_chance.clamp(0.0, 1.0);
+        self
+    }
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+    pub fn with_disconnected_caves(mut self) -> Self {
+        self.connected = false;
+        self
+    }
+
+    pub fn plan(&self) -> FloorPlan {
+        let mut plan = FloorPlan::new(self.size);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for pos in plan.positions() {
+            let floor = plan.is_inner(pos) && rng.random::<f32>() >= self.wall_chance;
+            plan.set_floor(pos, floor);
+        }
+        for _ in 0..self.iterations {
+            let previous = plan.clone();
+            for pos in previous.positions().filter(|pos| previous.is_inner(*pos)) {
+                let walls = (-1..=1)
+                    .flat_map(|x| (-1..=1).map(move |y| IVec2::new(x, y)))
+                    .filter(|offset| *offset != IVec2::ZERO && !previous.is_floor(pos + *offset))
+                    .count() as u32;
+                if walls > self.threshold {
+                    plan.set_floor(pos, false);
+                } else if walls < self.threshold {
+                    plan.set_floor(pos, true);
+                }
+            }
+        }
+        if self.connected {
+            plan.keep_largest_region();
+        }
+        plan
+    }
+    pub fn generate<TYP: TileTypeAble>(
+        &self,
+        origin: IVec3,
+        tiles: &LevelTiles<TYP>,
+    ) -> Vec<TileRecord<TYP>> {
+        self.plan().to_records(origin, tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::TestTile;
+
+    #[test]
+    fn same_seed_gives_same_plans() {
+        let size = IVec2::new(40, 30);
+        assert_eq!(
+            BspDungeon::new(9, size).plan(),
+            BspDungeon::new(9, size).plan()
+        );
+        assert_eq!(
+            DrunkardWalk::new(9, size).plan(),
+            DrunkardWalk::new(9, size).plan()
+        );
+        assert_eq!(
+            CaveAutomaton::new(9, size).plan(),
+            CaveAutomaton::new(9, size).plan()
+        );
+    }
+
+    #[test]
+    fn bsp_rooms_stay_inside_the_bounds() {
+        let size = IVec2::new(40, 30);
+        for seed in 0..20 {
+            let dungeon = BspDungeon::new(seed, size);
+            let plan = dungeon.plan();
+            assert!(plan.floor_count() > 0);
+            // Rooms and the corridors between their centers keep off the border
+            for pos in plan.positions().filter(|pos| plan.is_floor(*pos)) {
+                assert!(pos.cmpge(IVec2::ONE).all() && pos.cmplt(size - 1).all());
+            }
+
+            let origin = IVec3::new(-5, 2, 3);
+            let tiles = LevelTiles::new(TestTile::Ground).with_walls(TestTile::Stone, 2);
+            let max = origin + IVec3::new(size.x - 1, 1, size.y - 1);
+            for record in dungeon.generate(origin, &tiles) {
+                assert!(record.map_pos.cmpge(origin).all() && record.map_pos.cmple(max).all());
+            }
+        }
+    }
+}
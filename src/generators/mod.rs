@@ -0,0 +1,36 @@
+//! Procedural generators producing [`TileRecord`](crate::io::TileRecord)s, to be placed with
+//! [`TileMapCommands::place_batch`](crate::map_commands::TileMapCommands::place_batch).
+mod dungeon;
+mod noise;
+mod terrain;
+mod wfc;
+
+pub use dungeon::{BspDungeon, CaveAutomaton, DrunkardWalk, FloorPlan, LevelTiles};
+pub use noise::FbmNoise;
+pub use terrain::{
+    TerrainBand, TerrainBiome, TerrainGeneration, TerrainGenerator, TerrainOrientation,
+};
+pub use wfc::{WfcError, WfcGenerator, WfcTile, WfcTileSet};
+
+/// Tile type of the generator tests.
+#[cfg(test)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+    bevy::prelude::Component,
+    bevy::prelude::Reflect,
+)]
+enum TestTile {
+    #[default]
+    Ground,
+    Stone,
+    Water,
+}
+#[cfg(test)]
+impl crate::marker_traits::TileTypeAble for TestTile {}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+
+/// Seeded fractal (fBm) gradient noise in 2D.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FbmNoise {
+    pub seed: u32,
+    pub octaves: u32,
+    /// Frequency of the first octave, in cycles per cell.
+    pub frequency: f32,
+    /// Frequency factor from one octave to the next.
+    pub lacunarity: f32,
+    /// Amplitude factor from one octave to the next.
+    pub gain: f32,
+}
+impl Default for FbmNoise {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            octaves: 4,
+            frequency: 0.05,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+impl FbmNoise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Noise value at `pos`, roughly in `0.0..=1.0`.
+    pub fn sample(&self, pos: Vec2) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut total_amplitude = 0.0;
+        let mut frequency = self.frequency;
+        for octave in 0..self.octaves {
+            let seed = self.seed.wrapping_add(octave.wrapping_mul(0x9E37_79B9));
+            sum += gradient_noise(pos * frequency, seed) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        (sum / total_amplitude * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// Perlin style gradient noise in about `-1.0..=1.0`.
+fn gradient_noise(pos: Vec2, seed: u32) -> f32 {
+    let cell = pos.floor();
+    let local = pos - cell;
+    let cell = cell.as_ivec2();
+    let corner = |offset: IVec2| {
+        let angle = hash(cell + offset, seed) as f32 / u32::MAX as f32 * std::f32::consts::TAU;
+        Vec2::from_angle(angle).dot(local - offset.as_vec2())
+    };
+    let fade = local * local * local * (local * (local * 6.0 - 15.0) + 10.0);
+    let bottom = corner(IVec2::new(0, 0)).lerp(corner(IVec2::new(1, 0)), fade.x);
+    let top = corner(IVec2::new(0, 1)).lerp(corner(IVec2::new(1, 1)), fade.x);
+    bottom.lerp(top, fade.y) * std::f32::consts::SQRT_2
+}
+
+fn hash(cell: IVec2, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (cell.x as u32).wrapping_mul(0x85EB_CA6B)
+        ^ (cell.y as u32).wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^ (h >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let positions = (0..64).map(|i| Vec2::new(i as f32 * 1.7, i as f32 * -0.3));
+        let samples = |noise: FbmNoise| positions.clone().map(|pos| noise.sample(pos)).collect();
+        let first: Vec<f32> = samples(FbmNoise::new(3));
+        assert_eq!(first, samples(FbmNoise::new(3)));
+        assert_ne!(first, samples(FbmNoise::new(4)));
+        assert!(first.iter().all(|value| (0.0..=1.0).contains(value)));
+    }
+}
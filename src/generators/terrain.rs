@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+
+use crate::generators::FbmNoise;
+use crate::io::TileRecord;
+use crate::map_commands::TileMapCommands;
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMapConfig;
+
+/// How tiles of a [`TerrainBand`] are turned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainOrientation {
+    Fixed(u8),
+    /// The orientation whose +X axis points closest to the highest neighbor.
+    Uphill,
+}
+
+/// Tile used for surface cells within a range of heights and slopes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainBand<TYP: TileTypeAble> {
+    pub typ: TYP,
+    /// Map `y` of the surface cell.
+    pub heights: RangeInclusive<i32>,
+    /// Largest height difference to the side neighbors, in cells.
+    pub slopes: RangeInclusive<i32>,
+    pub orientation: TerrainOrientation,
+}
+impl<TYP: TileTypeAble> TerrainBand<TYP> {
+    pub fn new(typ: TYP) -> Self {
+        Self {
+            typ,
+            heights: i32::MIN..=i32::MAX,
+            slopes: 0..=i32::MAX,
+            orientation: TerrainOrientation::Fixed(0),
+        }
+    }
+    pub fn with_heights(mut self, heights: RangeInclusive<i32>) -> Self {
+        self.heights = heights;
+        self
+    }
+    pub fn with_slopes(mut self, slopes: RangeInclusive<i32>) -> Self {
+        self.slopes = slopes;
+        self
+    }
+    pub fn with_orientation(mut self, orientation: TerrainOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+}
+
+/// Bands used where the biome noise is at most `max_value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainBiome<TYP: TileTypeAble> {
+    pub max_value: f32,
+    pub bands: Vec<TerrainBand<TYP>>,
+}
+
+/// Builds terrain from a seeded heightmap. The surface cell of every column gets the first
+/// matching band, columns without one are left empty.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct TerrainGenerator<TYP: TileTypeAble> {
+    pub height_noise: FbmNoise,
+    pub biome_noise: FbmNoise,
+    pub bands: Vec<TerrainBand<TYP>>,
+    /// Replace `bands` where present, chosen by the biome noise.
+    pub biomes: Vec<TerrainBiome<TYP>>,
+    /// Type of the cells below the surface, left empty if `None`.
+    pub fill: Option<TYP>,
+}
+impl<TYP: TileTypeAble> TerrainGenerator<TYP> {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            height_noise: FbmNoise::new(seed),
+            biome_noise: FbmNoise::new(seed.wrapping_add(1)).with_frequency(0.01),
+            bands: Vec::new(),
+            biomes: Vec::new(),
+            fill: None,
+        }
+    }
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.height_noise.seed = seed;
+        self.biome_noise.seed = seed.wrapping_add(1);
+        self
+    }
+    pub fn with_band(mut self, band: TerrainBand<TYP>) -> Self {
+        self.bands.push(band);
+        self
+    }
+    pub fn with_biome(mut self, max_value: f32, bands: Vec<TerrainBand<TYP>>) -> Self {
+        self.biomes.push(TerrainBiome { max_value, bands });
+        self
+    }
+    pub fn with_fill(mut self, fill: TYP) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    /// Surface height of the column at `x`, `z`, between `min_y` and `max_y`.
+    pub fn height(&self, x: i32, z: i32, min_y: i32, max_y: i32) -> i32 {
+        let value = self.height_noise.sample(Vec2::new(x as f32, z as f32));
+        min_y + (value * (max_y - min_y) as f32).round() as i32
+    }
+
+    /// Tiles for the columns from `min` to `max`, with heights between `min.y` and `max.y`.
+    pub fn generate(&self, config: &TileMapConfig, min: IVec3, max: IVec3) -> Vec<TileRecord<TYP>> {
+        let sides: Vec<(Vec3, IVec2)> = config
+            .next_tile_dir
+            .iter()
+            .filter(|(_, offset)| offset.y == 0)
+            .map(|(normal, offset)| (*normal, offset.xz()))
+            .collect();
+        let mut records = Vec::new();
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let height = self.height(x, z, min.y, max.y);
+                let (uphill, slope) = sides
+                    .iter()
+                    .map(|(normal, offset)| {
+                        let neighbor = IVec2::new(x, z) + *offset;
+                        let rise = self.height(neighbor.x, neighbor.y, min.y, max.y) - height;
+                        (*normal, rise)
+                    })
+                    .max_by_key(|(_, rise)| rise.abs())
+                    .unwrap_or((Vec3::ZERO, 0));
+                let Some(band) = self.bands_at(x, z).iter().find(|band| {
+                    band.heights.contains(&height) && band.slopes.contains(&slope.abs())
+                }) else {
+                    continue;
+                };
+                let orientation = match band.orientation {
+                    TerrainOrientation::Fixed(orientation) => orientation,
+                    TerrainOrientation::Uphill => facing(config, uphill * slope.signum() as f32),
+                };
+                records.push(TileRecord::new(
+                    IVec3::new(x, height, z),
+                    orientation,
+                    band.typ.clone(),
+                ));
+                if let Some(fill) = &self.fill {
+                    records.extend(
+                        (min.y..height)
+                            .map(|y| TileRecord::new(IVec3::new(x, y, z), 0, fill.clone())),
+                    );
+                }
+            }
+        }
+        records
+    }
+
+    /// Runs [`TerrainGenerator::generate`] on the [`AsyncComputeTaskPool`].
+    pub fn spawn_task(
+        &self,
+        config: &TileMapConfig,
+        min: IVec3,
+        max: IVec3,
+    ) -> Task<Vec<TileRecord<TYP>>> {
+        let generator = self.clone();
+        let config = config.clone();
+        AsyncComputeTaskPool::get().spawn(async move { generator.generate(&config, min, max) })
+    }
+
+    fn bands_at(&self, x: i32, z: i32) -> &[TerrainBand<TYP>] {
+        if self.biomes.is_empty() {
+            return &self.bands;
+        }
+        let value = self.biome_noise.sample(Vec2::new(x as f32, z as f32));
+        let biome = self
+            .biomes
+            .iter()
+            .find(|biome| value <= biome.max_value)
+            .unwrap_or(self.biomes.last().unwrap());
+        &biome.bands
+    }
+}
+
+/// Orientation whose rotated +X axis is closest to `dir`.
+fn facing(config: &TileMapConfig, dir: Vec3) -> u8 {
+    if dir == Vec3::ZERO {
+        return 0;
+    }
+    config
+        .possible_orientations
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| {
+            (**a * Vec3::X)
+                .dot(dir)
+                .total_cmp(&(**b * Vec3::X).dot(dir))
+        })
+        .map(|(orientation, _)| orientation as u8)
+        .unwrap_or(0)
+}
+
+/// Terrain being generated in the background and the last result kept as preview.
+#[derive(Resource)]
+pub struct TerrainGeneration<TYP: TileTypeAble, MARKER: MarkerAble> {
+    task: Option<Task<Vec<TileRecord<TYP>>>>,
+    /// Place the result on the map once the task is done.
+    apply: bool,
+    pub preview: Vec<TileRecord<TYP>>,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TerrainGeneration<TYP, MARKER> {
+    fn default() -> Self {
+        Self {
+            task: None,
+            apply: false,
+            preview: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TerrainGeneration<TYP, MARKER> {
+    /// Starts generating, replacing any generation still running. With `apply` the result is
+    /// placed right away, otherwise it is kept as preview.
+    pub fn start(&mut self, task: Task<Vec<TileRecord<TYP>>>, apply: bool) {
+        self.task = Some(task);
+        self.apply = apply;
+    }
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+    /// Places the preview, or the result of the running generation once it is done.
+    pub fn apply(&mut self) {
+        self.apply = true;
+    }
+
+    pub fn update(mut generation: ResMut<Self>, mut map_commands: TileMapCommands<TYP, MARKER>) {
+        if let Some(task) = generation.task.as_mut()
+            && let Some(records) = block_on(poll_once(task))
+        {
+            generation.task = None;
+            generation.preview = records;
+        }
+        if generation.apply && generation.task.is_none() {
+            generation.apply = false;
+            let records = std::mem::take(&mut generation.preview);
+            map_commands.place_batch(records);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::TestTile;
+
+    #[test]
+    fn same_seed_gives_same_terrain_inside_the_region() {
+        let generator = TerrainGenerator::new(5)
+            .with_band(TerrainBand::new(TestTile::Stone).with_heights(4..=7))
+            .with_band(TerrainBand::new(TestTile::Ground))
+            .with_fill(TestTile::Water);
+        let config = TileMapConfig::default();
+        let (min, max) = (IVec3::new(-8, 0, -8), IVec3::new(7, 7, 7));
+
+        let records = generator.generate(&config, min, max);
+        assert!(!records.is_empty());
+        assert_eq!(records, generator.generate(&config, min, max));
+        assert!(
+            records.iter().all(|record| {
+                record.map_pos.cmpge(min).all() && record.map_pos.cmple(max).all()
+            })
+        );
+    }
+}
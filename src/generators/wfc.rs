@@ -0,0 +1,474 @@
+use bevy::prelude::*;
+use derive_more::Display;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::io::{self, TileRecord};
+use crate::marker_traits::TileTypeAble;
+use crate::resources::TileMapConfig;
+use crate::utils::{self, FileFormat};
+
+/// Tile a [`WfcTileSet`] is built from. Faces are matched through their sockets, written for
+/// orientation `0` and keyed by the offsets of
+/// [`TileMapConfig::next_tile_dir`](crate::resources::TileMapConfig).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfcTile<TYP: TileTypeAble> {
+    /// `None` leaves the cell empty.
+    pub typ: Option<TYP>,
+    pub weight: f32,
+    /// Two faces fit if their sockets are equal. Faces without a socket fit anything.
+    pub sockets: HashMap<IVec3, String>,
+    /// Also use the tile in every other orientation.
+    pub rotate: bool,
+}
+impl<TYP: TileTypeAble> WfcTile<TYP> {
+    pub fn new(typ: TYP) -> Self {
+        Self {
+            typ: Some(typ),
+            weight: 1.0,
+            sockets: HashMap::new(),
+            rotate: true,
+        }
+    }
+    pub fn empty() -> Self {
+        Self {
+            typ: None,
+            weight: 1.0,
+            sockets: HashMap::new(),
+            rotate: false,
+        }
+    }
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+    pub fn with_socket(mut self, dir: IVec3, socket: impl Into<String>) -> Self {
+        self.sockets.insert(dir, socket.into());
+        self
+    }
+    pub fn without_rotation(mut self) -> Self {
+        self.rotate = false;
+        self
+    }
+}
+
+/// Oriented tiles and which of them may sit next to each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfcTileSet<TYP: TileTypeAble> {
+    /// Type, orientation and weight of every oriented tile.
+    pub variants: Vec<(Option<TYP>, u8, f32)>,
+    dirs: Vec<IVec3>,
+    /// `allowed[a][dir][b]`: variant `b` may be the neighbor of `a` in direction `dirs[dir]`.
+    allowed: Vec<Vec<Vec<bool>>>,
+}
+impl<TYP: TileTypeAble> WfcTileSet<TYP> {
+    /// Builds the set from socket constraints, rotating tiles by every orientation of `config`.
+    pub fn from_tiles(tiles: &[WfcTile<TYP>], config: &TileMapConfig) -> Self {
+        let dirs = Self::dirs(config);
+        let mut variants = Vec::new();
+        let mut sockets: Vec<Vec<Option<String>>> = Vec::new();
+        for tile in tiles.iter() {
+            let first = sockets.len();
+            let orientations = match tile.rotate {
+                true => config.possible_orientations.len(),
+                false => 1,
+            };
+            for orientation in 0..orientations {
+                // Face `dir` of the rotated tile is the face `rotation⁻¹ * dir` of the tile
+                let inverse = config.possible_orientations[orientation].inverse();
+                let faces = dirs
+                    .iter()
+                    .map(|dir| {
+                        let base_dir = config.rotate_offset(inverse, *dir);
+                        tile.sockets.get(&base_dir).cloned()
+                    })
+                    .collect();
+                // Symmetric tiles look the same in several orientations
+                if sockets[first..].contains(&faces) {
+                    continue;
+                }
+                variants.push((tile.typ.clone(), orientation as u8, tile.weight));
+                sockets.push(faces);
+            }
+        }
+
+        let mut allowed = vec![vec![vec![false; variants.len()]; dirs.len()]; variants.len()];
+        for a in 0..variants.len() {
+            for (dir_index, dir) in dirs.iter().enumerate() {
+                let Some(opposite) = dirs.iter().position(|other| *other == -*dir) else {
+                    continue;
+                };
+                for b in 0..variants.len() {
+                    allowed[a][dir_index][b] = match (&sockets[a][dir_index], &sockets[b][opposite])
+                    {
+                        (Some(face_a), Some(face_b)) => face_a == face_b,
+                        _ => true,
+                    };
+                }
+            }
+        }
+        Self {
+            variants,
+            dirs,
+            allowed,
+        }
+    }
+
+    /// Learns tiles, weights and adjacencies from an example map. Cells inside the bounds of
+    /// the example without a tile become an empty tile.
+    pub fn learn(records: &[TileRecord<TYP>], config: &TileMapConfig) -> Self {
+        let dirs = Self::dirs(config);
+        // Sorted keys keep the variant order independent of the record order
+        let mut keys: BTreeMap<(String, u8), usize> = BTreeMap::new();
+        let mut cells: HashMap<IVec3, (String, u8)> = HashMap::new();
+        for record in records.iter() {
+            let key = (utils::type_key(&record.typ), record.orientation);
+            keys.insert(key.clone(), 0);
+            cells.insert(record.map_pos, key);
+        }
+        let empty_key = (String::new(), 0);
+        let Some((min, max)) = records
+            .iter()
+            .map(|record| (record.map_pos, record.map_pos))
+            .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)))
+        else {
+            return Self {
+                variants: Vec::new(),
+                dirs,
+                allowed: Vec::new(),
+            };
+        };
+        if cells.len() < box_positions(min, max).count() {
+            keys.insert(empty_key.clone(), 0);
+        }
+
+        let mut variants = Vec::new();
+        for (index, (key, variant)) in keys.iter_mut().enumerate() {
+            *variant = index;
+            let typ = records
+                .iter()
+                .find(|record| key.0 == utils::type_key(&record.typ))
+                .filter(|_| *key != empty_key)
+                .map(|record| record.typ.clone());
+            variants.push((typ, key.1, 0.0));
+        }
+        let variant_at = |map_pos: IVec3| keys[cells.get(&map_pos).unwrap_or(&empty_key)];
+
+        let mut allowed = vec![vec![vec![false; variants.len()]; dirs.len()]; variants.len()];
+        for map_pos in box_positions(min, max) {
+            let a = variant_at(map_pos);
+            variants[a].2 += 1.0;
+            for (dir_index, dir) in dirs.iter().enumerate() {
+                let neighbor = map_pos + *dir;
+                if neighbor.cmplt(min).any() || neighbor.cmpgt(max).any() {
+                    continue;
+                }
+                allowed[a][dir_index][variant_at(neighbor)] = true;
+            }
+        }
+        Self {
+            variants,
+            dirs,
+            allowed,
+        }
+    }
+
+    /// Learns from a map file as written by [`SaveMapEvent`](crate::events::SaveMapEvent).
+    pub fn learn_from_file(
+        path: impl AsRef<Path>,
+        format: FileFormat,
+        config: &TileMapConfig,
+    ) -> Result<Self, String> {
+        let records = io::read_tiles_from_file::<TYP>(path.as_ref(), format)?;
+        Ok(Self::learn(&records, config))
+    }
+
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    fn dirs(config: &TileMapConfig) -> Vec<IVec3> {
+        config.next_tile_dir.iter().map(|(_, dir)| *dir).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum WfcError {
+    #[display("the tile set is empty")]
+    EmptyTileSet,
+    /// No tile fits the cell at `map_pos`, even before any choice was made.
+    #[display("no tile fits at {map_pos}")]
+    Contradiction { map_pos: IVec3 },
+    /// Still ran into contradictions after backtracking `backtracks` times.
+    #[display("gave up at {map_pos} after {backtracks} backtracks")]
+    BacktrackLimit { map_pos: IVec3, backtracks: usize },
+}
+impl std::error::Error for WfcError {}
+
+/// Fills a box of cells with tiles of a [`WfcTileSet`] so that all neighbors fit. The same seed
+/// always gives the same result.
+#[derive(Debug, Clone)]
+pub struct WfcGenerator<'a, TYP: TileTypeAble> {
+    tile_set: &'a WfcTileSet<TYP>,
+    seed: u64,
+    max_backtracks: usize,
+}
+impl<'a, TYP: TileTypeAble> WfcGenerator<'a, TYP> {
+    pub fn new(tile_set: &'a WfcTileSet<TYP>, seed: u64) -> Self {
+        Self {
+            tile_set,
+            seed,
+            max_backtracks: 1000,
+        }
+    }
+    pub fn with_max_backtracks(mut self, max_backtracks: usize) -> Self {
+        self.max_backtracks = max_backtracks;
+        self
+    }
+
+    /// Tiles for every cell from `min` to `max`, both inclusive. Empty cells are left out.
+    pub fn generate(&self, min: IVec3, max: IVec3) -> Result<Vec<TileRecord<TYP>>, WfcError> {
+        if self.tile_set.is_empty() {
+            return Err(WfcError::EmptyTileSet);
+        }
+        let mut solver = Solver::new(self.tile_set, min, max);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let all_cells: Vec<usize> = (0..solver.counts.len()).collect();
+        if let Err(cell) = solver.propagate(all_cells) {
+            return Err(WfcError::Contradiction {
+                map_pos: solver.map_pos(cell),
+            });
+        }
+
+        // Choices made so far with the trail length before each of them
+        let mut decisions: Vec<(usize, usize, usize)> = Vec::new();
+        let mut backtracks = 0;
+        while let Some(cell) = solver.lowest_entropy_cell(&mut rng) {
+            let tile = solver.choose(cell, &mut rng);
+            decisions.push((cell, tile, solver.trail.len()));
+            let mut result = solver.collapse(cell, tile);
+            while let Err(failed) = result {
+                backtracks += 1;
+                let Some((cell, tile, trail_len)) = decisions.pop() else {
+                    return Err(WfcError::Contradiction {
+                        map_pos: solver.map_pos(failed),
+                    });
+                };
+                if backtracks > self.max_backtracks {
+                    return Err(WfcError::BacktrackLimit {
+                        map_pos: solver.map_pos(failed),
+                        backtracks: self.max_backtracks,
+                    });
+                }
+                // Undo the choice and rule it out
+                solver.undo(trail_len);
+                solver.ban(cell, tile);
+                result = match solver.counts[cell] {
+                    0 => Err(cell),
+                    _ => solver.propagate(vec![cell]),
+                };
+            }
+        }
+        Ok(solver.records())
+    }
+}
+
+struct Solver<'a, TYP: TileTypeAble> {
+    tile_set: &'a WfcTileSet<TYP>,
+    min: IVec3,
+    size: IVec3,
+    /// `possible[cell * variants + variant]`
+    possible: Vec<bool>,
+    counts: Vec<usize>,
+    /// Every ban as `(cell, variant)`, to undo them when backtracking.
+    trail: Vec<(usize, usize)>,
+}
+impl<'a, TYP: TileTypeAble> Solver<'a, TYP> {
+    fn new(tile_set: &'a WfcTileSet<TYP>, min: IVec3, max: IVec3) -> Self {
+        let size = (max - min + 1).max(IVec3::ZERO);
+        let cells = (size.x * size.y * size.z) as usize;
+        Self {
+            tile_set,
+            min,
+            size,
+            possible: vec![true; cells * tile_set.len()],
+            counts: vec![tile_set.len(); cells],
+            trail: Vec::new(),
+        }
+    }
+    fn map_pos(&self, cell: usize) -> IVec3 {
+        let cell = cell as i32;
+        let x = cell % self.size.x;
+        let y = cell / self.size.x % self.size.y;
+        let z = cell / (self.size.x * self.size.y);
+        self.min + IVec3::new(x, y, z)
+    }
+    fn cell(&self, map_pos: IVec3) -> Option<usize> {
+        let local = map_pos - self.min;
+        if local.cmplt(IVec3::ZERO).any() || local.cmpge(self.size).any() {
+            return None;
+        }
+        Some((local.x + local.y * self.size.x + local.z * self.size.x * self.size.y) as usize)
+    }
+    fn is_possible(&self, cell: usize, variant: usize) -> bool {
+        self.possible[cell * self.tile_set.len() + variant]
+    }
+    fn ban(&mut self, cell: usize, variant: usize) {
+        let index = cell * self.tile_set.len() + variant;
+        if self.possible[index] {
+            self.possible[index] = false;
+            self.counts[cell] -= 1;
+            self.trail.push((cell, variant));
+        }
+    }
+    fn undo(&mut self, trail_len: usize) {
+        for (cell, variant) in self.trail.drain(trail_len..) {
+            self.possible[cell * self.tile_set.len() + variant] = true;
+            self.counts[cell] += 1;
+        }
+    }
+
+    /// Undecided cell with the fewest options left, ties are broken randomly.
+    fn lowest_entropy_cell(&self, rng: &mut StdRng) -> Option<usize> {
+        let mut best: Option<(f32, usize)> = None;
+        for (cell, count) in self.counts.iter().enumerate() {
+            if *count <= 1 {
+                continue;
+            }
+            let entropy = *count as f32 + rng.random::<f32>() * 0.5;
+            if best.is_none_or(|(best_entropy, _)| entropy < best_entropy) {
+                best = Some((entropy, cell));
+            }
+        }
+        best.map(|(_, cell)| cell)
+    }
+    /// Picks one of the options of `cell` by weight.
+    fn choose(&self, cell: usize, rng: &mut StdRng) -> usize {
+        let options: Vec<usize> = (0..self.tile_set.len())
+            .filter(|variant| self.is_possible(cell, *variant))
+            .collect();
+        let weight = |variant: &usize| self.tile_set.variants[*variant].2.max(0.0);
+        let total: f32 = options.iter().map(weight).sum();
+        if total <= 0.0 {
+            return options[rng.random_range(0..options.len())];
+        }
+        let mut pick = rng.random::<f32>() * total;
+        for variant in options.iter() {
+            pick -= weight(variant);
+            if pick < 0.0 {
+                return *variant;
+            }
+        }
+        *options.last().unwrap()
+    }
+    fn collapse(&mut self, cell: usize, tile: usize) -> Result<(), usize> {
+        for variant in 0..self.tile_set.len() {
+            if variant != tile {
+                self.ban(cell, variant);
+            }
+        }
+        self.propagate(vec![cell])
+    }
+    /// Bans options of neighbors that no option of a changed cell allows. Returns the cell left
+    /// without options on a contradiction.
+    fn propagate(&mut self, mut changed: Vec<usize>) -> Result<(), usize> {
+        let variants = self.tile_set.len();
+        while let Some(cell) = changed.pop() {
+            let map_pos = self.map_pos(cell);
+            for (dir_index, dir) in self.tile_set.dirs.iter().enumerate() {
+                let Some(neighbor) = self.cell(map_pos + *dir) else {
+                    continue;
+                };
+                let mut banned = false;
+                for b in 0..variants {
+                    if !self.is_possible(neighbor, b) {
+                        continue;
+                    }
+                    let supported = (0..variants).any(|a| {
+                        self.is_possible(cell, a) && self.tile_set.allowed[a][dir_index][b]
+                    });
+                    if !supported {
+                        self.ban(neighbor, b);
+                        banned = true;
+                    }
+                }
+                if self.counts[neighbor] == 0 {
+                    return Err(neighbor);
+                }
+                if banned {
+                    changed.push(neighbor);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn records(&self) -> Vec<TileRecord<TYP>> {
+        (0..self.counts.len())
+            .filter_map(|cell| {
+                let variant =
+                    (0..self.tile_set.len()).find(|variant| self.is_possible(cell, *variant))?;
+                let (typ, orientation, _) = &self.tile_set.variants[variant];
+                Some(TileRecord::new(
+                    self.map_pos(cell),
+                    *orientation,
+                    typ.clone()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+fn box_positions(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::TestTile;
+
+    fn generate(
+        tiles: &[WfcTile<TestTile>],
+        seed: u64,
+    ) -> Result<Vec<TileRecord<TestTile>>, WfcError> {
+        let tile_set = WfcTileSet::from_tiles(tiles, &TileMapConfig::default());
+        WfcGenerator::new(&tile_set, seed).generate(IVec3::ZERO, IVec3::new(5, 0, 5))
+    }
+
+    #[test]
+    fn same_seed_gives_same_tiles() {
+        let tiles = [
+            WfcTile::new(TestTile::Ground)
+                .with_socket(IVec3::X, "road")
+                .with_socket(IVec3::NEG_X, "road"),
+            WfcTile::new(TestTile::Stone).with_socket(IVec3::X, "rock"),
+            WfcTile::new(TestTile::Water).with_weight(2.0),
+        ];
+        let first = generate(&tiles, 7).unwrap();
+        assert_eq!(first.len(), 36);
+        assert_eq!(first, generate(&tiles, 7).unwrap());
+    }
+
+    #[test]
+    fn contradictory_rules_are_an_error() {
+        // The +X face of the only tile never fits its -X face
+        let tiles = [WfcTile::new(TestTile::Stone)
+            .without_rotation()
+            .with_socket(IVec3::X, "a")
+            .with_socket(IVec3::NEG_X, "b")];
+        assert!(matches!(
+            generate(&tiles, 7),
+            Err(WfcError::Contradiction { .. })
+        ));
+        assert!(matches!(generate(&[], 7), Err(WfcError::EmptyTileSet)));
+    }
+}
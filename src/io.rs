@@ -0,0 +1,381 @@
+use bevy::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::utils::FileFormat;
+
+/// Used to group tiles by type, e.g. in the map statistics.
+pub use crate::utils::variant_name;
+
+/// One tile as it is stored in a map file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TileRecord<TYP> {
+    pub map_pos: IVec3,
+    pub orientation: u8,
+    pub typ: TYP,
+    /// Registered [`TileDataAble`](crate::marker_traits::TileDataAble) components by their key.
+    pub data: BTreeMap<String, Value>,
+}
+impl<TYP> TileRecord<TYP> {
+    pub fn new(map_pos: IVec3, orientation: u8, typ: TYP) -> Self {
+        Self {
+            map_pos,
+            orientation,
+            typ,
+            data: BTreeMap::new(),
+        }
+    }
+    pub fn with_data(mut self, data: BTreeMap<String, Value>) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+/// First column of the Csv row holding the seed.
+#[cfg(feature = "csv")]
+const SEED_ROW: &str = "seed";
+
+/// Contents of a whole map file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapFile<TYP> {
+    /// Seed of the [`TileMapRng`](crate::rng::TileMapRng) the map was edited with.
+    pub seed: Option<u64>,
+    pub tiles: Vec<TileRecord<TYP>>,
+}
+
+pub fn extension(format: FileFormat) -> &'static str {
+    match format {
+        FileFormat::Json => "json",
+        FileFormat::Csv => "csv",
+    }
+}
+/// Format of a map file by its extension.
+pub fn format_of(path: &Path) -> Option<FileFormat> {
+    match path.extension()?.to_str()? {
+        "json" => Some(FileFormat::Json),
+        "csv" => Some(FileFormat::Csv),
+        _ => None,
+    }
+}
+/// Path of the whole map file for `source_path`.
+pub fn map_path(source_path: &str, format: FileFormat) -> PathBuf {
+    PathBuf::from(format!("{}.{}", source_path, extension(format)))
+}
+/// Path of the file storing a single chunk, inside the `source_path` directory.
+pub fn chunk_path(source_path: &str, chunk: IVec3, format: FileFormat) -> PathBuf {
+    Path::new(source_path).join(format!(
+        "{}_{}_{}.{}",
+        chunk.x,
+        chunk.y,
+        chunk.z,
+        extension(format)
+    ))
+}
+/// Chunk position encoded in a file name written by [`chunk_path`].
+pub fn parse_chunk_file_name(path: &Path, format: FileFormat) -> Option<IVec3> {
+    if path.extension()? != extension(format) {
+        return None;
+    }
+    let mut coords = path.file_stem()?.to_str()?.split('_');
+    let x = coords.next()?.parse().ok()?;
+    let y = coords.next()?.parse().ok()?;
+    let z = coords.next()?.parse().ok()?;
+    coords.next().is_none().then_some(IVec3::new(x, y, z))
+}
+
+/// Reads all well formed tiles, skipping entries that can't be parsed.
+pub fn read_tiles_from_file<TYP: DeserializeOwned>(
+    path: &Path,
+    format: FileFormat,
+) -> Result<Vec<TileRecord<TYP>>, String> {
+    read_map_from_file(path, format).map(|map| map.tiles)
+}
+/// Reads the seed and all well formed tiles, skipping entries that can't be parsed.
+pub fn read_map<TYP: DeserializeOwned>(
+    reader: impl Read,
+    format: FileFormat,
+) -> Result<MapFile<TYP>, String> {
+    read_map_checked(reader, format).map(|(map, _)| map)
+}
+pub fn read_map_from_file<TYP: DeserializeOwned>(
+    path: &Path,
+    format: FileFormat,
+) -> Result<MapFile<TYP>, String> {
+    read_map_from_file_checked(path, format).map(|(map, _)| map)
+}
+/// Like [`read_map`], but also returns the entries that were skipped and why.
+#[cfg_attr(not(all(feature = "csv", feature = "json")), allow(unused_variables))]
+pub fn read_map_checked<TYP: DeserializeOwned>(
+    reader: impl Read,
+    format: FileFormat,
+) -> Result<(MapFile<TYP>, Vec<SkippedEntry>), String> {
+    match format {
+        #[cfg(feature = "json")]
+        FileFormat::Json => read_json(reader),
+        #[cfg(feature = "csv")]
+        FileFormat::Csv => read_csv(reader),
+        #[allow(unreachable_patterns)]
+        format => Err(disabled_format(format)),
+    }
+}
+pub fn read_map_from_file_checked<TYP: DeserializeOwned>(
+    path: &Path,
+    format: FileFormat,
+) -> Result<(MapFile<TYP>, Vec<SkippedEntry>), String> {
+    let Ok(file) = File::open(path) else {
+        return Err(format!("file {:?} doesn't exist", path));
+    };
+    read_map_checked(file, format).map_err(|e| format!("{} ({:?})", e, path))
+}
+
+/// Entry of a map file that couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    /// Where the entry is, like `line 4` of a Csv file or `tile 2` of a Json file.
+    pub location: String,
+    pub reason: String,
+}
+impl SkippedEntry {
+    pub fn new(location: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+fn read_json<TYP: DeserializeOwned>(
+    reader: impl Read,
+) -> Result<(MapFile<TYP>, Vec<SkippedEntry>), String> {
+    let values: Value = serde_json::from_reader(reader)
+        .map_err(|e| format!("Tile Map source files contains invalid Json: {}", e))?;
+    let seed = values.get("seed").and_then(Value::as_u64);
+    let Some(Value::Array(tiles)) = values.get("tiles") else {
+        let map = MapFile {
+            seed,
+            tiles: Vec::new(),
+        };
+        return Ok((map, Vec::new()));
+    };
+
+    let mut records = Vec::new();
+    let mut skipped = Vec::new();
+    tiles
+        .iter()
+        .enumerate()
+        .for_each(|(index, tile)| match read_json_tile(tile) {
+            Ok(record) => records.push(record),
+            Err(reason) => skipped.push(SkippedEntry::new(format!("tile {}", index), reason)),
+        });
+    let map = MapFile {
+        seed,
+        tiles: records,
+    };
+    Ok((map, skipped))
+}
+#[cfg(feature = "json")]
+fn read_json_tile<TYP: DeserializeOwned>(tile: &Value) -> Result<TileRecord<TYP>, String> {
+    fn field<T: DeserializeOwned>(tile: &Value, key: &str) -> Result<T, String> {
+        let value = tile.get(key).ok_or(format!("missing \"{}\"", key))?;
+        serde_json::from_value(value.clone()).map_err(|e| format!("invalid \"{}\": {}", key, e))
+    }
+    let pos: IVec3 = field(tile, "pos")?;
+    let orientation: u8 = field(tile, "dir")?;
+    let typ: TYP = field(tile, "typ")?;
+    let data: BTreeMap<String, Value> = tile
+        .get("data")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    Ok(TileRecord::new(pos, orientation, typ).with_data(data))
+}
+#[cfg(feature = "csv")]
+fn read_csv<TYP: DeserializeOwned>(
+    reader: impl Read,
+) -> Result<(MapFile<TYP>, Vec<SkippedEntry>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(reader);
+
+    let mut seed = None;
+    let mut records = Vec::new();
+    let mut skipped = Vec::new();
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|position| position.line()).unwrap_or(0);
+                skipped.push(SkippedEntry::new(format!("line {}", line), e.to_string()));
+                continue;
+            }
+        };
+        // The seed is stored in a row of its own, which older readers skip as malformed
+        if record.get(0) == Some(SEED_ROW) {
+            seed = record.get(1).and_then(|seed| seed.parse().ok());
+            continue;
+        }
+        match read_csv_tile(&record) {
+            Ok(tile) => records.push(tile),
+            Err(reason) => {
+                let line = record
+                    .position()
+                    .map(|position| position.line())
+                    .unwrap_or(0);
+                skipped.push(SkippedEntry::new(format!("line {}", line), reason));
+            }
+        }
+    }
+    let map = MapFile {
+        seed,
+        tiles: records,
+    };
+    Ok((map, skipped))
+}
+#[cfg(feature = "csv")]
+fn read_csv_tile<TYP: DeserializeOwned>(
+    record: &csv::StringRecord,
+) -> Result<TileRecord<TYP>, String> {
+    if record.len() < 5 {
+        return Err(format!("expected 5 or 6 fields, found {}", record.len()));
+    }
+    let coordinate = |index: usize, name: &str| {
+        record[index]
+            .parse::<i32>()
+            .map_err(|_| format!("invalid {} \"{}\"", name, &record[index]))
+    };
+    let pos = IVec3::new(
+        coordinate(0, "x")?,
+        coordinate(1, "y")?,
+        coordinate(2, "z")?,
+    );
+    let dir = record[3]
+        .parse::<u8>()
+        .map_err(|_| format!("invalid dir \"{}\"", &record[3]))?;
+    let typ: TYP = serde_json::from_str(&record[4])
+        .map_err(|e| format!("invalid typ {}: {}", &record[4], e))?;
+    // Files written before the data column have no sixth field
+    let data: BTreeMap<String, Value> = record
+        .get(5)
+        .filter(|data| !data.is_empty())
+        .and_then(|data| serde_json::from_str(data).ok())
+        .unwrap_or_default();
+    Ok(TileRecord::new(pos, dir, typ).with_data(data))
+}
+
+/// Error for a format whose feature is turned off.
+fn disabled_format(format: FileFormat) -> String {
+    format!(
+        "{} map files need the \"{}\" feature of bevy_grid_map",
+        format,
+        extension(format)
+    )
+}
+
+pub fn write_tiles_to_file<TYP: Serialize>(
+    path: &Path,
+    format: FileFormat,
+    tiles: &[TileRecord<TYP>],
+) -> Result<(), String> {
+    write_map_to_file(path, format, None, tiles)
+}
+#[cfg_attr(not(all(feature = "csv", feature = "json")), allow(unused_variables))]
+pub fn write_map<TYP: Serialize>(
+    writer: impl Write,
+    format: FileFormat,
+    seed: Option<u64>,
+    tiles: &[TileRecord<TYP>],
+) -> Result<(), String> {
+    match format {
+        #[cfg(feature = "json")]
+        FileFormat::Json => write_json(writer, seed, tiles),
+        #[cfg(feature = "csv")]
+        FileFormat::Csv => write_csv(writer, seed, tiles),
+        #[allow(unreachable_patterns)]
+        format => Err(disabled_format(format)),
+    }
+}
+pub fn write_map_to_file<TYP: Serialize>(
+    path: &Path,
+    format: FileFormat,
+    seed: Option<u64>,
+    tiles: &[TileRecord<TYP>],
+) -> Result<(), String> {
+    let Ok(file) = File::create(path) else {
+        return Err(format!(
+            "couldn't create storage file for tile_map, directory may not exist: {:?}",
+            path
+        ));
+    };
+    write_map(file, format, seed, tiles)
+}
+#[cfg(feature = "json")]
+fn write_json<TYP: Serialize>(
+    mut writer: impl Write,
+    seed: Option<u64>,
+    tiles: &[TileRecord<TYP>],
+) -> Result<(), String> {
+    let tiles_json: Vec<Value> = tiles
+        .iter()
+        .map(|tile| {
+            let pos = tile.map_pos;
+            let dir = tile.orientation;
+            match tile.data.is_empty() {
+                true => serde_json::json!({ "pos": pos, "dir": dir, "typ": tile.typ }),
+                false => {
+                    serde_json::json!({ "pos": pos, "dir": dir, "typ": tile.typ, "data": tile.data })
+                }
+            }
+        })
+        .collect();
+    let value = match seed {
+        Some(seed) => serde_json::json!({ "seed": seed, "tiles": tiles_json }),
+        None => serde_json::json!({ "tiles": tiles_json }),
+    };
+    serde_json::to_writer_pretty(&mut writer, &value).map_err(|e| e.to_string())
+}
+#[cfg(feature = "csv")]
+fn write_csv<TYP: Serialize>(
+    writer: impl Write,
+    seed: Option<u64>,
+    tiles: &[TileRecord<TYP>],
+) -> Result<(), String> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(true)
+        .from_writer(writer);
+
+    // Write header
+    let _ = writer.write_record(["x", "y", "z", "dir", "typ", "data"]);
+    if let Some(seed) = seed {
+        let _ = writer.write_record([SEED_ROW, &seed.to_string(), "", "", "", ""]);
+    }
+
+    for tile in tiles.iter() {
+        let pos = tile.map_pos;
+        let dir = tile.orientation;
+
+        let Ok(typ_json) = serde_json::to_string(&tile.typ) else {
+            continue;
+        };
+        let data_json = match tile.data.is_empty() {
+            true => String::new(),
+            false => serde_json::to_string(&tile.data).unwrap_or_default(),
+        };
+
+        let _ = writer.write_record(&[
+            pos.x.to_string(),
+            pos.y.to_string(),
+            pos.z.to_string(),
+            dir.to_string(),
+            typ_json,
+            data_json,
+        ]);
+    }
+
+    writer.flush().map_err(|e| e.to_string())
+}
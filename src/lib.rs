@@ -1,35 +1,74 @@
+mod autotile;
+mod chunks;
 mod components;
+#[cfg(feature = "editor")]
 mod editor;
 mod events;
+#[cfg(feature = "generators")]
+mod generators;
+/// Reading and writing map files, shared by the map events and the `bevy_grid_map` command line
+/// tool.
+pub mod io;
+mod map_commands;
 mod marker_traits;
+mod orientation;
+mod pathfinding;
+mod picking;
+#[cfg(feature = "render")]
+mod render;
 mod resources;
+mod rng;
+mod stats;
+mod streaming;
+mod tile_data;
+mod topology;
 mod utils;
+#[cfg(feature = "render")]
+mod visuals;
+
+#[cfg(feature = "vox")]
+pub use bevy_vox_scene;
 
 use bevy::prelude::*;
 
 use std::marker::PhantomData;
 
+use crate::chunks::ChunkTracker;
+#[cfg(feature = "editor")]
 use crate::editor::TileMapEditorPlugin;
-use crate::events::{ClearMapEvent, LoadMapEvent, SaveMapEvent};
-use crate::marker_traits::{MarkerAble, TileTypeAble};
-use crate::prelude::Tile;
+use crate::events::{ClearMapEvent, LoadMapEvent, SaveMapEvent, TilePlaced, TileRemoved};
+#[cfg(feature = "generators")]
+use crate::generators::TerrainGeneration;
+use crate::marker_traits::{MarkerAble, TileDataAble, TileTypeAble};
+use crate::prelude::{Tile, TileMapRoot};
 use crate::resources::{TileMap, TileMapConfig};
+use crate::rng::TileMapRng;
+use crate::streaming::TileMapStreaming;
+use crate::tile_data::{TileDataEntry, TileDataRegistry};
 
 pub struct TileMapPlugin<TYP: TileTypeAble, MARKER: MarkerAble> {
     pub edit: bool,
     pub config: TileMapConfig,
+    /// Extra tile components saved and loaded with the map.
+    pub tile_data: Vec<TileDataEntry>,
     _typ: PhantomData<TYP>,
     _marker: PhantomData<MARKER>,
 }
-impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapPlugin<TYP, MARKER> {
-    pub fn new() -> Self {
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileMapPlugin<TYP, MARKER> {
+    fn default() -> Self {
         Self {
             edit: false,
             config: TileMapConfig::default(),
+            tile_data: Vec::new(),
             _typ: PhantomData,
             _marker: PhantomData,
         }
     }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapPlugin<TYP, MARKER> {
+    pub fn new() -> Self {
+        Self::default()
+    }
     pub fn in_edit_mod(mut self) -> Self {
         self.edit = true;
         self
@@ -38,24 +77,63 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapPlugin<TYP, MARKER> {
         self.config = config;
         self
     }
+    /// Saves and loads the tile component `D` with the map and makes it editable in the
+    /// editor's inspector.
+    pub fn with_tile_data<D: TileDataAble>(mut self) -> Self {
+        self.tile_data.push(TileDataEntry::new::<D>());
+        self
+    }
 }
 impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileMapPlugin<TYP, MARKER> {
     fn build(&self, app: &mut App) {
         app.insert_resource(TileMap::<MARKER>::new(self.config.clone()));
+        app.insert_resource(TileMapRng::<MARKER>::new(self.config.seed));
+        app.register_type::<TYP>();
+        let tile_data = TileDataRegistry::<MARKER>::new(self.tile_data.clone());
+        tile_data.register_types(app);
+        app.insert_resource(tile_data);
 
         app.add_observer(LoadMapEvent::<TYP, MARKER>::on);
         app.add_observer(SaveMapEvent::<TYP, MARKER>::on);
         app.add_observer(ClearMapEvent::<TYP, MARKER>::on);
-        app.add_observer(Tile::<MARKER>::on_added);
+        app.add_observer(Tile::<MARKER>::on_inserted);
+        app.add_observer(Tile::<MARKER>::on_replaced);
+        app.add_observer(TilePlaced::<TYP, MARKER>::on_tile_added);
+        app.add_observer(TileRemoved::<TYP, MARKER>::on_tile_removed);
+        app.add_observer(TileMapRoot::<MARKER>::on_tile_added);
+        app.add_observer(TileMapRoot::<MARKER>::on_root_added);
+        app.add_observer(Tile::<MARKER>::on_map_set);
+        app.add_observer(Tile::<MARKER>::on_map_unset);
+        app.init_resource::<TileMapStreaming<TYP, MARKER>>();
+        app.add_systems(Update, TileMapStreaming::<TYP, MARKER>::update);
+        #[cfg(feature = "generators")]
+        app.init_resource::<TerrainGeneration<TYP, MARKER>>();
+        #[cfg(feature = "generators")]
+        app.add_systems(Update, TerrainGeneration::<TYP, MARKER>::update);
+        app.add_systems(
+            PostUpdate,
+            (
+                chunks::mark_changed_chunks::<TYP, MARKER>,
+                ChunkTracker::<MARKER>::update.after(TransformSystems::Propagate),
+                TileMapRoot::<MARKER>::sync_origin.after(TransformSystems::Propagate),
+                TileMap::<MARKER>::sync_entity_origins.after(TransformSystems::Propagate),
+            ),
+        );
 
+        #[cfg(feature = "editor")]
         if self.edit {
             app.add_plugins(TileMapEditorPlugin::<TYP, MARKER>::default());
         }
+        #[cfg(not(feature = "editor"))]
+        if self.edit {
+            println!("the tile map editor needs the \"editor\" feature of bevy_grid_map");
+        }
 
         if self.config.load_from_source {
             app.add_systems(Startup, |mut commands: Commands| {
-                commands.trigger(LoadMapEvent::<TYP, MARKER>::new(utils::FileFormat::Csv));
-                commands.trigger(LoadMapEvent::<TYP, MARKER>::new(utils::FileFormat::Json));
+                for format in utils::FileFormat::enabled() {
+                    commands.trigger(LoadMapEvent::<TYP, MARKER>::new(format));
+                }
             });
         }
     }
@@ -63,9 +141,39 @@ impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileMapPlugin<TYP, MARKER
 
 pub mod prelude {
     pub use crate::TileMapPlugin;
-    pub use crate::components::Tile;
+    pub use crate::autotile::{
+        AutoTilePlugin, AutoTileRule, AutoTileRules, AutoTiled, NeighborRule,
+    };
+    pub use crate::chunks::{
+        ChunkEnteredEvent, ChunkLeftEvent, ChunkTracker, ChunkedTiles, TileChunk,
+    };
+    pub use crate::components::{MapTiles, Tile, TileMapRoot, TileOfMap};
+    #[cfg(feature = "editor")]
+    pub use crate::editor::{EditorMode, TileMapEditor, TileMapEditorPlugin};
     pub use crate::events::*;
+    #[cfg(feature = "generators")]
+    pub use crate::generators::{
+        BspDungeon, CaveAutomaton, DrunkardWalk, FbmNoise, FloorPlan, LevelTiles, TerrainBand,
+        TerrainBiome, TerrainGeneration, TerrainGenerator, TerrainOrientation, WfcError,
+        WfcGenerator, WfcTile, WfcTileSet,
+    };
+    pub use crate::io::TileRecord;
+    pub use crate::map_commands::{MoveResult, OccupiedPolicy, PlaceResult, TileMapCommands};
     pub use crate::marker_traits::*;
+    pub use crate::picking::GridHit;
+    #[cfg(feature = "render")]
+    pub use crate::picking::TileMapPickingPlugin;
+    #[cfg(feature = "render")]
+    pub use crate::render::{ChunkMesh, TileMesh, TileMeshRenderPlugin, TileMeshes};
     pub use crate::resources::{TileMap, TileMapConfig};
+    pub use crate::rng::TileMapRng;
+    pub use crate::stats::TileMapStats;
+    pub use crate::streaming::{TileMapStreamer, TileMapStreaming};
+    pub use crate::tile_data::{TileDataEntry, TileDataRegistry};
+    pub use crate::topology::{CuboidGrid, GridTopology, HexPrismGrid, SharedTopology};
     pub use crate::utils::FileFormat;
+    #[cfg(feature = "render")]
+    pub use crate::visuals::{
+        TileVisual, TileVisualChild, TileVisualSource, TileVisuals, TileVisualsPlugin,
+    };
 }
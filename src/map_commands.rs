@@ -0,0 +1,271 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Tile;
+use crate::events::TileChanged;
+use crate::io::TileRecord;
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+
+/// What placing or moving a tile does when the target cell is already occupied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OccupiedPolicy {
+    /// Despawn the tile in the cell and put the new one there.
+    #[default]
+    Replace,
+    /// Leave the map unchanged.
+    Reject,
+    /// Put the new tile into the first free cell above.
+    Stack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceResult {
+    /// The cell was free.
+    Placed(Entity),
+    /// The tile `old` was despawned to make room.
+    Replaced { entity: Entity, old: Entity },
+    /// The cell was occupied, the tile went to `map_pos` instead.
+    Stacked { entity: Entity, map_pos: IVec3 },
+    /// The cell was occupied by `occupant`, nothing changed.
+    Rejected { occupant: Entity },
+}
+impl PlaceResult {
+    /// The tile that was placed, if any.
+    pub fn entity(&self) -> Option<Entity> {
+        match self {
+            Self::Placed(entity) | Self::Replaced { entity, .. } | Self::Stacked { entity, .. } => {
+                Some(*entity)
+            }
+            Self::Rejected { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveResult {
+    Moved(Entity),
+    /// The tile `old` in the target cell was despawned to make room.
+    Replaced {
+        entity: Entity,
+        old: Entity,
+    },
+    /// The target was occupied, the tile went to `map_pos` instead.
+    Stacked {
+        entity: Entity,
+        map_pos: IVec3,
+    },
+    Rejected {
+        occupant: Entity,
+    },
+    /// There is no tile at the source position.
+    Missing,
+}
+
+/// Edits a tile map while keeping one tile per cell.
+///
+/// The index of the [`TileMap`] is updated right away, so later calls in the same system already
+/// see the result of earlier ones. Occupied cells are handled by
+/// [`TileMapConfig::occupied_policy`](crate::resources::TileMapConfig::occupied_policy) unless a
+/// policy is passed explicitly.
+#[derive(SystemParam)]
+pub struct TileMapCommands<'w, 's, TYP: TileTypeAble, MARKER: MarkerAble> {
+    commands: Commands<'w, 's>,
+    tile_map: ResMut<'w, TileMap<MARKER>>,
+    tiles: Query<'w, 's, (&'static Tile<MARKER>, &'static TYP)>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapCommands<'_, '_, TYP, MARKER> {
+    pub fn tile_map(&self) -> &TileMap<MARKER> {
+        &self.tile_map
+    }
+    pub fn get(&self, map_pos: IVec3) -> Option<Entity> {
+        self.tile_map.tiles.get(&map_pos).copied()
+    }
+    /// Tile and type at `map_pos`, as of the last time commands were applied.
+    pub fn get_tile(&self, map_pos: IVec3) -> Option<(&Tile<MARKER>, &TYP)> {
+        self.tiles.get(self.get(map_pos)?).ok()
+    }
+
+    pub fn place(&mut self, map_pos: IVec3, typ: TYP, orientation: u8) -> PlaceResult {
+        let policy = self.tile_map.config.occupied_policy;
+        self.place_with_policy(map_pos, typ, orientation, policy)
+    }
+    pub fn place_with_policy(
+        &mut self,
+        map_pos: IVec3,
+        typ: TYP,
+        orientation: u8,
+        policy: OccupiedPolicy,
+    ) -> PlaceResult {
+        let Some(occupant) = self.get(map_pos) else {
+            return PlaceResult::Placed(self.spawn(map_pos, typ, orientation));
+        };
+        match policy {
+            OccupiedPolicy::Reject => PlaceResult::Rejected { occupant },
+            OccupiedPolicy::Replace => {
+                self.remove(map_pos);
+                let entity = self.spawn(map_pos, typ, orientation);
+                PlaceResult::Replaced {
+                    entity,
+                    old: occupant,
+                }
+            }
+            OccupiedPolicy::Stack => {
+                let map_pos = self.free_cell_above(map_pos);
+                let entity = self.spawn(map_pos, typ, orientation);
+                PlaceResult::Stacked { entity, map_pos }
+            }
+        }
+    }
+    pub fn place_batch(
+        &mut self,
+        tiles: impl IntoIterator<Item = TileRecord<TYP>>,
+    ) -> Vec<PlaceResult> {
+        tiles
+            .into_iter()
+            .map(|tile| self.place(tile.map_pos, tile.typ, tile.orientation))
+            .collect()
+    }
+
+    /// Despawns the tile at `map_pos` and returns it.
+    pub fn remove(&mut self, map_pos: IVec3) -> Option<Entity> {
+        let entity = self.tile_map.tiles.remove(&map_pos)?;
+        self.commands.entity(entity).despawn();
+        Some(entity)
+    }
+    pub fn remove_batch(&mut self, positions: impl IntoIterator<Item = IVec3>) -> Vec<Entity> {
+        positions
+            .into_iter()
+            .filter_map(|map_pos| self.remove(map_pos))
+            .collect()
+    }
+
+    /// Changes type and orientation of the tile at `map_pos` in place and triggers
+    /// [`TileChanged`]. Returns `None` if the cell is empty.
+    pub fn replace(&mut self, map_pos: IVec3, typ: TYP, orientation: u8) -> Option<Entity> {
+        let entity = self.get(map_pos)?;
+        let old = self
+            .tiles
+            .get(entity)
+            .ok()
+            .map(|(tile, old_typ)| (tile.orientation, old_typ.clone()));
+        self.commands
+            .entity(entity)
+            .insert((Tile::<MARKER>::new(map_pos, orientation), typ.clone()));
+        // Tiles spawned earlier in the same system have no old state yet
+        if let Some((old_orientation, old_typ)) = old {
+            self.commands.trigger(TileChanged::<TYP, MARKER>::new(
+                entity,
+                map_pos,
+                old_orientation,
+                orientation,
+                old_typ,
+                typ,
+            ));
+        }
+        Some(entity)
+    }
+
+    pub fn move_tile(&mut self, from: IVec3, to: IVec3) -> MoveResult {
+        let policy = self.tile_map.config.occupied_policy;
+        self.move_tile_with_policy(from, to, policy)
+    }
+    pub fn move_tile_with_policy(
+        &mut self,
+        from: IVec3,
+        to: IVec3,
+        policy: OccupiedPolicy,
+    ) -> MoveResult {
+        let Some(entity) = self.get(from) else {
+            return MoveResult::Missing;
+        };
+        if from == to {
+            return MoveResult::Moved(entity);
+        }
+        let result = match self.get(to) {
+            None => MoveResult::Moved(entity),
+            Some(occupant) => match policy {
+                OccupiedPolicy::Reject => return MoveResult::Rejected { occupant },
+                OccupiedPolicy::Replace => {
+                    self.remove(to);
+                    MoveResult::Replaced {
+                        entity,
+                        old: occupant,
+                    }
+                }
+                OccupiedPolicy::Stack => {
+                    self.tile_map.tiles.remove(&from);
+                    let map_pos = self.free_cell_above(to);
+                    self.tile_map.tiles.insert(from, entity);
+                    MoveResult::Stacked { entity, map_pos }
+                }
+            },
+        };
+        let to = match result {
+            MoveResult::Stacked { map_pos, .. } => map_pos,
+            _ => to,
+        };
+        self.relocate(entity, from, to);
+        result
+    }
+    pub fn move_batch(
+        &mut self,
+        moves: impl IntoIterator<Item = (IVec3, IVec3)>,
+    ) -> Vec<MoveResult> {
+        moves
+            .into_iter()
+            .map(|(from, to)| self.move_tile(from, to))
+            .collect()
+    }
+
+    /// Exchanges the tiles of two cells, either of which may be empty.
+    pub fn swap(&mut self, a: IVec3, b: IVec3) {
+        if a == b {
+            return;
+        }
+        let (tile_a, tile_b) = (self.get(a), self.get(b));
+        if let Some(entity) = tile_a {
+            self.relocate(entity, a, b);
+        }
+        if let Some(entity) = tile_b {
+            self.relocate(entity, b, a);
+        }
+    }
+    pub fn swap_batch(&mut self, swaps: impl IntoIterator<Item = (IVec3, IVec3)>) {
+        swaps.into_iter().for_each(|(a, b)| self.swap(a, b));
+    }
+
+    fn spawn(&mut self, map_pos: IVec3, typ: TYP, orientation: u8) -> Entity {
+        let entity = self
+            .commands
+            .spawn((Tile::<MARKER>::new(map_pos, orientation), typ))
+            .id();
+        self.tile_map.tiles.insert(map_pos, entity);
+        entity
+    }
+    /// Moves `entity` from `from` to `to`, overwriting the index entry of `to`.
+    fn relocate(&mut self, entity: Entity, from: IVec3, to: IVec3) {
+        if self.tile_map.tiles.get(&from) == Some(&entity) {
+            self.tile_map.tiles.remove(&from);
+        }
+        self.tile_map.tiles.insert(to, entity);
+        // Read the tile once the command is applied, it may have been spawned by an earlier
+        // call in the same system
+        self.commands
+            .entity(entity)
+            .queue(move |mut entity: EntityWorldMut| {
+                let Some(orientation) = entity.get::<Tile<MARKER>>().map(|tile| tile.orientation)
+                else {
+                    return;
+                };
+                entity.insert(Tile::<MARKER>::new(to, orientation));
+            });
+    }
+    fn free_cell_above(&self, mut map_pos: IVec3) -> IVec3 {
+        while self.tile_map.tiles.contains_key(&map_pos) {
+            map_pos += IVec3::Y;
+        }
+        map_pos
+    }
+}
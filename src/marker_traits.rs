@@ -1,11 +1,37 @@
 use bevy::prelude::*;
-use enum2egui::GuiInspect;
+use bevy::reflect::GetTypeRegistration;
 use serde::Serialize;
 
 pub trait MarkerAble: 'static + Send + Sync {}
 impl<M: 'static + Send + Sync> MarkerAble for M {}
 
+/// Type of a tile, registered for reflection so the editor can show and edit it.
 pub trait TileTypeAble:
-    'static + GuiInspect + Component + Serialize + for<'de> serde::Deserialize<'de> + Clone + Default
+    'static
+    + Reflect
+    + GetTypeRegistration
+    + Component
+    + Serialize
+    + for<'de> serde::Deserialize<'de>
+    + Clone
+    + Default
 {
 }
+
+/// Extra component saved and loaded with each tile, like chest contents or lock ids. Register
+/// it with [`TileMapPlugin::with_tile_data`](crate::TileMapPlugin::with_tile_data).
+pub trait TileDataAble:
+    'static
+    + Reflect
+    + GetTypeRegistration
+    + Component
+    + Serialize
+    + for<'de> serde::Deserialize<'de>
+    + Clone
+    + Default
+{
+    /// Name of the data in map files, the short type name by default.
+    fn data_key() -> String {
+        ShortName::of::<Self>().to_string()
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use crate::marker_traits::MarkerAble;
+use crate::resources::TileMap;
+
+impl<MARKER: MarkerAble> TileMap<MARKER> {
+    /// Orientation whose rotation is closest to `rotation`.
+    pub fn rotation_to_orientation(&self, rotation: Quat) -> u8 {
+        self.config
+            .possible_orientations
+            .iter()
+            .enumerate()
+            // `q` and `-q` are the same rotation, so compare the absolute dot product
+            .max_by(|(_, a), (_, b)| a.dot(rotation).abs().total_cmp(&b.dot(rotation).abs()))
+            .map(|(orientation, _)| orientation as u8)
+            .unwrap_or(0)
+    }
+    /// Orientation of turning by `first`, then by `second`.
+    pub fn compose_orientations(&self, first: u8, second: u8) -> u8 {
+        let rotation = self.orientation_to_rotation(second) * self.orientation_to_rotation(first);
+        self.rotation_to_orientation(rotation)
+    }
+    /// Orientation undoing `orientation`.
+    pub fn invert_orientation(&self, orientation: u8) -> u8 {
+        self.rotation_to_orientation(self.orientation_to_rotation(orientation).inverse())
+    }
+    /// Grid direction `dir` turned by `orientation`.
+    pub fn rotate_dir(&self, orientation: u8, dir: IVec3) -> IVec3 {
+        self.config
+            .rotate_offset(self.orientation_to_rotation(orientation), dir)
+    }
+    /// Orientation after turning `orientation` by `steps` of the smallest turn around the
+    /// world `axis` (`X`, `Y` or `Z`). Stays the same if no orientation is that turn away.
+    pub fn rotate_orientation(&self, orientation: u8, axis: Vec3, steps: i32) -> u8 {
+        let rotation = Quat::from_axis_angle(axis, self.turn_angle(axis) * steps as f32)
+            * self.orientation_to_rotation(orientation);
+        let turned = self.rotation_to_orientation(rotation);
+        let reached = self.orientation_to_rotation(turned).dot(rotation).abs() > 0.999;
+        match reached {
+            true => turned,
+            false => orientation,
+        }
+    }
+    /// Orientation turning the tile's +Y towards the grid direction `up`, the one closest to
+    /// `preferred` if there are several. `None` if no orientation does.
+    pub fn orientation_facing(&self, up: IVec3, preferred: u8) -> Option<u8> {
+        let preferred = self.orientation_to_rotation(preferred);
+        (0..self.config.possible_orientations.len() as u8)
+            .filter(|orientation| self.rotate_dir(*orientation, IVec3::Y) == up)
+            .max_by(|a, b| {
+                let a = self.orientation_to_rotation(*a).dot(preferred).abs();
+                let b = self.orientation_to_rotation(*b).dot(preferred).abs();
+                a.total_cmp(&b)
+            })
+    }
+    /// Smallest turn around `axis` between the orientations: a full turn divided by the number
+    /// of orientations keeping `axis` in place, a quarter turn if there are none.
+    fn turn_angle(&self, axis: Vec3) -> f32 {
+        let count = self
+            .config
+            .possible_orientations
+            .iter()
+            .filter(|rotation| (**rotation * axis).dot(axis) > 0.999)
+            .count();
+        match count {
+            0 | 1 => FRAC_PI_2,
+            count => TAU / count as f32,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::marker_traits::MarkerAble;
+use crate::resources::TileMap;
+
+impl<MARKER: MarkerAble> TileMap<MARKER> {
+    /// Cells next to `map_pos` along [`TileMapConfig::next_tile_dir`](crate::resources::TileMapConfig).
+    pub fn neighbors(&self, map_pos: IVec3) -> impl Iterator<Item = IVec3> + '_ {
+        self.config
+            .next_tile_dir
+            .iter()
+            .map(move |(_, offset)| map_pos + *offset)
+    }
+
+    /// Cheapest path from `start` to `goal` with A*, both included, moving between neighbors.
+    ///
+    /// `cost` is the cost of entering a cell, `None` if it can't be entered. The path is the
+    /// cheapest one as long as no cost is below `1.0`. Gives up after visiting `max_visited`
+    /// cells.
+    pub fn find_path(
+        &self,
+        start: IVec3,
+        goal: IVec3,
+        max_visited: usize,
+        cost: impl Fn(IVec3) -> Option<f32>,
+    ) -> Option<Vec<IVec3>> {
+        let heuristic = |map_pos: IVec3| self.config.grid_distance(map_pos, goal) as f32;
+        let mut open = BinaryHeap::from([Node {
+            estimate: heuristic(start),
+            map_pos: start,
+        }]);
+        let mut costs: HashMap<IVec3, f32> = HashMap::from([(start, 0.0)]);
+        let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+        let mut visited = 0;
+
+        while let Some(Node { estimate, map_pos }) = open.pop() {
+            if map_pos == goal {
+                let mut path = vec![goal];
+                while let Some(previous) = came_from.get(path.last().unwrap()) {
+                    path.push(*previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let cost_here = costs[&map_pos];
+            // Skip outdated entries of cells reached cheaper in the meantime
+            if estimate > cost_here + heuristic(map_pos) {
+                continue;
+            }
+            visited += 1;
+            if visited > max_visited {
+                return None;
+            }
+            for neighbor in self.neighbors(map_pos) {
+                let Some(step) = cost(neighbor) else {
+                    continue;
+                };
+                let new_cost = cost_here + step;
+                if costs.get(&neighbor).is_some_and(|known| *known <= new_cost) {
+                    continue;
+                }
+                costs.insert(neighbor, new_cost);
+                came_from.insert(neighbor, map_pos);
+                open.push(Node {
+                    estimate: new_cost + heuristic(neighbor),
+                    map_pos: neighbor,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Entry of the A* open list, the lowest estimate first.
+struct Node {
+    estimate: f32,
+    map_pos: IVec3,
+}
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Node {}
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
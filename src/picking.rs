@@ -0,0 +1,270 @@
+#[cfg(feature = "render")]
+use bevy::picking::PickingSystems;
+#[cfg(feature = "render")]
+use bevy::picking::backend::ray::RayMap;
+#[cfg(feature = "render")]
+use bevy::picking::backend::{HitData, PointerHits};
+use bevy::prelude::*;
+#[cfg(feature = "render")]
+use std::marker::PhantomData;
+
+use crate::marker_traits::MarkerAble;
+use crate::resources::TileMap;
+
+/// First tile cell hit by a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridHit {
+    pub map_pos: IVec3,
+    pub entity: Entity,
+    /// Direction from the hit cell to the neighboring cell the ray came from.
+    pub normal: IVec3,
+    /// Distance along the ray, in world units.
+    pub distance: f32,
+    /// World position where the ray enters the cell.
+    pub point: Vec3,
+}
+
+impl<MARKER: MarkerAble> TileMap<MARKER> {
+    /// Walks the world space ray cell by cell through the tile grid and returns the first
+    /// occupied cell.
+    pub fn raycast(&self, ray: Ray3d, max_distance: f32) -> Option<GridHit> {
+        // Walk in the local space of the map root
+        let inverse = self.origin.affine().inverse();
+        let local_dir = inverse.transform_vector3(*ray.direction);
+        // Local units per world unit along the ray
+        let scale = local_dir.length();
+        let local_ray = Ray3d::new(
+            inverse.transform_point3(ray.origin),
+            Dir3::new(local_dir).ok()?,
+        );
+        let hit = match self.config.topology.is_cuboid() {
+            true => self.raycast_cuboid(local_ray, max_distance * scale),
+            false => self.raycast_marching(local_ray, max_distance * scale),
+        }?;
+        Some(GridHit {
+            distance: hit.distance / scale,
+            point: self.origin.transform_point(hit.point),
+            ..hit
+        })
+    }
+    /// Exact walk through cells that are boxes of `tile_size` centered on
+    /// [`TileMapConfig::grid_to_world`](crate::resources::TileMapConfig::grid_to_world).
+    fn raycast_cuboid(&self, ray: Ray3d, max_distance: f32) -> Option<GridHit> {
+        let (min, max) = self.occupied_bounds()?;
+        let size = self.config.tile_size;
+        // Grid space: cell `c` covers `c..c + 1` on every axis
+        let origin = ray.origin / size + 0.5;
+        let dir = *ray.direction / size;
+
+        // Clip the ray against the box of occupied cells
+        let box_min = min.as_vec3();
+        let box_max = (max + IVec3::ONE).as_vec3();
+        let mut t_enter = 0.0_f32;
+        let mut t_exit = max_distance;
+        let mut normal = IVec3::ZERO;
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if origin[axis] < box_min[axis] || origin[axis] >= box_max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (box_min[axis] - origin[axis]) / dir[axis];
+            let t1 = (box_max[axis] - origin[axis]) / dir[axis];
+            let (near, far) = (t0.min(t1), t0.max(t1));
+            if near > t_enter {
+                t_enter = near;
+                normal = IVec3::ZERO;
+                normal[axis] = -(dir[axis].signum() as i32);
+            }
+            t_exit = t_exit.min(far);
+        }
+        if t_enter > t_exit {
+            return None;
+        }
+
+        let start = origin + dir * t_enter;
+        let mut cell = start.floor().as_ivec3().clamp(min, max);
+        let step = dir.signum().as_ivec3();
+        let mut t_max = Vec3::splat(f32::INFINITY);
+        let mut t_delta = Vec3::splat(f32::INFINITY);
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                continue;
+            }
+            let boundary = match step[axis] > 0 {
+                true => cell[axis] as f32 + 1.0,
+                false => cell[axis] as f32,
+            };
+            t_max[axis] = (boundary - origin[axis]) / dir[axis];
+            t_delta[axis] = 1.0 / dir[axis].abs();
+        }
+        if normal == IVec3::ZERO {
+            // Started inside the box, report the face the ray points away from
+            let axis = dir.abs().max_position();
+            normal[axis] = -step[axis];
+        }
+
+        let mut t = t_enter;
+        while t <= t_exit {
+            if let Some(entity) = self.tiles.get(&cell) {
+                return Some(GridHit {
+                    map_pos: cell,
+                    entity: *entity,
+                    normal,
+                    distance: t,
+                    point: ray.get_point(t),
+                });
+            }
+            let axis = t_max.min_position();
+            t = t_max[axis];
+            t_max[axis] += t_delta[axis];
+            cell[axis] += step[axis];
+            normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+        }
+        None
+    }
+    /// Samples the ray in small steps through
+    /// [`TileMapConfig::world_to_grid`](crate::resources::TileMapConfig::world_to_grid), for any
+    /// layout.
+    fn raycast_marching(&self, ray: Ray3d, max_distance: f32) -> Option<GridHit> {
+        let (min, max) = self.occupied_bounds()?;
+        // Local box around the occupied cells
+        let size = self.config.tile_size;
+        let (world_min, world_max) = (0..8)
+            .map(|corner| {
+                let pick = |bit: i32, axis: usize| match corner >> bit & 1 {
+                    0 => min[axis],
+                    _ => max[axis],
+                };
+                self.config
+                    .grid_to_world(IVec3::new(pick(0, 0), pick(1, 1), pick(2, 2)))
+            })
+            .fold((Vec3::MAX, Vec3::MIN), |(a, b), corner| {
+                (a.min(corner), b.max(corner))
+            });
+        let (world_min, world_max) = (world_min - size, world_max + size);
+
+        let dir = *ray.direction;
+        let mut t_enter = 0.0_f32;
+        let mut t_exit = max_distance;
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if ray.origin[axis] < world_min[axis] || ray.origin[axis] > world_max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (world_min[axis] - ray.origin[axis]) / dir[axis];
+            let t1 = (world_max[axis] - ray.origin[axis]) / dir[axis];
+            t_enter = t_enter.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+
+        let step = size.min_element() * 0.1;
+        let mut previous: Option<IVec3> = None;
+        let mut t = t_enter;
+        while t <= t_exit {
+            let point = ray.get_point(t);
+            let cell = self.config.world_to_grid(point);
+            if previous == Some(cell) {
+                t += step;
+                continue;
+            }
+            if let Some(entity) = self.tiles.get(&cell) {
+                // Neighbor the ray came from, or the side facing the ray
+                let normal = previous
+                    .map(|previous| previous - cell)
+                    .filter(|normal| self.config.next_tile_dir.iter().any(|(_, d)| d == normal))
+                    .or_else(|| {
+                        self.config
+                            .next_tile_dir
+                            .iter()
+                            .max_by(|(a, _), (b, _)| a.dot(-dir).total_cmp(&b.dot(-dir)))
+                            .map(|(_, offset)| *offset)
+                    })
+                    .unwrap_or(IVec3::ZERO);
+                return Some(GridHit {
+                    map_pos: cell,
+                    entity: *entity,
+                    normal,
+                    distance: t,
+                    point,
+                });
+            }
+            previous = Some(cell);
+            t += step;
+        }
+        None
+    }
+    /// Empty cell next to the face `hit` entered through, found from the hit point in grid
+    /// space, so tiles attach to the clicked face whatever the tile size.
+    pub fn attach_pos(&self, hit: &GridHit) -> IVec3 {
+        let local_point = self.origin.affine().inverse().transform_point3(hit.point);
+        let normal = self
+            .config
+            .topology
+            .face_normal(hit.normal, self.config.tile_size);
+        let epsilon = self.config.tile_size.min_element() * 0.01;
+        let map_pos = self.config.world_to_grid(local_point + normal * epsilon);
+        // The hit point can lie inside the cell, then take the neighbor across the face
+        match map_pos == hit.map_pos {
+            true => hit.map_pos + hit.normal,
+            false => map_pos,
+        }
+    }
+    /// Smallest box of map positions containing all chunks with tiles.
+    fn occupied_bounds(&self) -> Option<(IVec3, IVec3)> {
+        self.tiles
+            .chunks()
+            .filter(|(_, chunk)| !chunk.tiles.is_empty())
+            .map(|(chunk, _)| self.tiles.chunk_bounds(*chunk))
+            .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)))
+    }
+}
+
+/// Picking backend reporting tile hits through [`TileMap::raycast`], so `Pointer` events reach
+/// tile entities without any meshes on them.
+#[cfg(feature = "render")]
+pub struct TileMapPickingPlugin<MARKER: MarkerAble>(PhantomData<MARKER>);
+#[cfg(feature = "render")]
+impl<MARKER: MarkerAble> Default for TileMapPickingPlugin<MARKER> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+#[cfg(feature = "render")]
+impl<MARKER: MarkerAble> Plugin for TileMapPickingPlugin<MARKER> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            update_hits::<MARKER>.in_set(PickingSystems::Backend),
+        );
+    }
+}
+
+#[cfg(feature = "render")]
+fn update_hits<MARKER: MarkerAble>(
+    ray_map: Res<RayMap>,
+    cameras: Query<&Camera>,
+    tile_map: Res<TileMap<MARKER>>,
+    mut pointer_hits_writer: MessageWriter<PointerHits>,
+) {
+    for (&ray_id, &ray) in ray_map.iter() {
+        let Ok(camera) = cameras.get(ray_id.camera) else {
+            continue;
+        };
+        let Some(hit) = tile_map.raycast(ray, f32::MAX) else {
+            continue;
+        };
+        let normal = tile_map.face_normal(hit.normal);
+        let hit_data = HitData::new(ray_id.camera, hit.distance, Some(hit.point), Some(normal));
+        let order = camera.order as f32;
+        pointer_hits_writer.write(PointerHits::new(
+            ray_id.pointer,
+            vec![(hit.entity, hit_data)],
+            order,
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::components::{Tile, TileMapRoot};
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+
+/// Mesh and material a tile type is drawn with when merged into its chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMesh {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    /// Applied to the mesh before the tile's own translation and rotation.
+    pub offset: Transform,
+}
+impl TileMesh {
+    pub fn new(mesh: Handle<Mesh>, material: Handle<StandardMaterial>) -> Self {
+        Self {
+            mesh,
+            material,
+            offset: Transform::IDENTITY,
+        }
+    }
+    pub fn with_offset(mut self, offset: Transform) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+type TileMeshFn<TYP> = dyn Fn(&TYP) -> Option<TileMesh> + Send + Sync;
+
+/// Maps tile types to their [`TileMesh`]. Types mapped to `None` aren't merged and can be drawn
+/// any other way.
+#[derive(Resource)]
+pub struct TileMeshes<TYP: TileTypeAble>(Arc<TileMeshFn<TYP>>);
+impl<TYP: TileTypeAble> TileMeshes<TYP> {
+    pub fn get(&self, typ: &TYP) -> Option<TileMesh> {
+        (self.0)(typ)
+    }
+}
+
+/// Entity drawing all tiles of one chunk that share a material.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct ChunkMesh<MARKER: MarkerAble> {
+    pub chunk: IVec3,
+    _marker: PhantomData<MARKER>,
+}
+
+/// Renders static tiles as one merged mesh per chunk and material instead of one entity each.
+/// Only chunks changed since their last rebuild, by their
+/// [`TileChunk::generation`](crate::chunks::TileChunk::generation), get rebuilt. Tile types with
+/// a [`TileMesh`] get no [`TileVisuals`](crate::visuals::TileVisuals) child.
+pub struct TileMeshRenderPlugin<TYP: TileTypeAble, MARKER: MarkerAble> {
+    meshes: Arc<TileMeshFn<TYP>>,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMeshRenderPlugin<TYP, MARKER> {
+    pub fn new(meshes: impl Fn(&TYP) -> Option<TileMesh> + Send + Sync + 'static) -> Self {
+        Self {
+            meshes: Arc::new(meshes),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileMeshRenderPlugin<TYP, MARKER> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TileMeshes::<TYP>(self.meshes.clone()));
+        app.add_systems(
+            PostUpdate,
+            rebuild_chunk_meshes::<TYP, MARKER>
+                .after(crate::chunks::mark_changed_chunks::<TYP, MARKER>)
+                .after(TileMapRoot::<MARKER>::sync_origin),
+        );
+    }
+}
+
+fn rebuild_chunk_meshes<TYP: TileTypeAble, MARKER: MarkerAble>(
+    tile_map: Res<TileMap<MARKER>>,
+    tiles: Query<(&Tile<MARKER>, &TYP)>,
+    tile_meshes: Res<TileMeshes<TYP>>,
+    chunk_meshes: Query<(Entity, &ChunkMesh<MARKER>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    // Generation of each chunk as of its last rebuild
+    mut built: Local<HashMap<IVec3, u64>>,
+    mut commands: Commands,
+) {
+    // Chunks changed since their last rebuild, and chunks dropped from the index since
+    let mut rebuild: Vec<(IVec3, Option<u64>)> = tile_map
+        .tiles
+        .chunks()
+        .filter(|(pos, chunk)| built.get(*pos) != Some(&chunk.generation))
+        .map(|(pos, chunk)| (*pos, Some(chunk.generation)))
+        .collect();
+    rebuild.extend(
+        built
+            .keys()
+            .filter(|pos| tile_map.tiles.chunk(**pos).is_none())
+            .map(|pos| (*pos, None)),
+    );
+
+    for (chunk, generation) in rebuild {
+        // Group the tiles of the chunk by material
+        let mut groups: HashMap<AssetId<StandardMaterial>, (Handle<StandardMaterial>, Vec<Mesh>)> =
+            HashMap::new();
+        let mut missing_mesh = false;
+        for (_, entity) in tile_map.tiles.tiles_in_chunk(chunk) {
+            let Ok((tile, typ)) = tiles.get(*entity) else {
+                continue;
+            };
+            let Some(tile_mesh) = tile_meshes.get(typ) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(&tile_mesh.mesh) else {
+                missing_mesh = true;
+                continue;
+            };
+            let transform = tile_map.tile_transform(tile) * tile_mesh.offset;
+            groups
+                .entry(tile_mesh.material.id())
+                .or_insert_with(|| (tile_mesh.material.clone(), Vec::new()))
+                .1
+                .push(mesh.clone().transformed_by(transform));
+        }
+        // Try again once all meshes of the chunk are loaded
+        if missing_mesh {
+            continue;
+        }
+        match generation {
+            Some(generation) => built.insert(chunk, generation),
+            None => built.remove(&chunk),
+        };
+
+        chunk_meshes
+            .iter()
+            .filter(|(_, chunk_mesh)| chunk_mesh.chunk == chunk)
+            .for_each(|(entity, _)| commands.entity(entity).despawn());
+
+        for (material, tile_meshes) in groups.into_values() {
+            let mut tile_meshes = tile_meshes.into_iter();
+            let Some(mut merged) = tile_meshes.next() else {
+                continue;
+            };
+            for mesh in tile_meshes {
+                if let Err(e) = merged.merge(&mesh) {
+                    println!("couldn't merge tile mesh into chunk {}: {}", chunk, e);
+                }
+            }
+            let chunk_mesh = commands
+                .spawn((
+                    ChunkMesh::<MARKER> {
+                        chunk,
+                        _marker: PhantomData,
+                    },
+                    Mesh3d(meshes.add(merged)),
+                    MeshMaterial3d(material),
+                    Transform::IDENTITY,
+                ))
+                .id();
+            if let Some(root) = tile_map.root {
+                commands.entity(chunk_mesh).insert(ChildOf(root));
+            }
+        }
+    }
+}
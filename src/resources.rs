@@ -1,25 +1,40 @@
-use bevy::ecs::entity::Entity;
 use bevy::math::IVec3;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::f32::consts::PI;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
+use crate::chunks::ChunkedTiles;
+use crate::components::Tile;
+use crate::map_commands::OccupiedPolicy;
 use crate::marker_traits::MarkerAble;
+use crate::topology::{CuboidGrid, GridTopology, HexPrismGrid, SharedTopology};
 use crate::utils;
 
-#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+/// Tile index and config of a map. Used as resource for the map of `MARKER`, or as component
+/// on map entities, which [`TileOfMap`](crate::components::TileOfMap) tiles belong to.
+#[derive(Debug, Clone, PartialEq, Resource, Component, Serialize, Deserialize)]
+#[require(Transform)]
+#[cfg_attr(feature = "render", require(Visibility))]
 pub struct TileMap<MARKER: MarkerAble> {
-    pub tiles: HashMap<IVec3, Entity>,
+    pub tiles: ChunkedTiles,
     pub config: TileMapConfig,
+    /// Transform of the [`TileMapRoot`](crate::components::TileMapRoot) or map entity,
+    /// identity without one.
+    #[serde(skip)]
+    pub origin: GlobalTransform,
+    /// The [`TileMapRoot`](crate::components::TileMapRoot) or map entity, if any.
+    #[serde(skip)]
+    pub root: Option<Entity>,
     pub _marker: PhantomData<MARKER>,
 }
 impl<MARKER: MarkerAble> TileMap<MARKER> {
     pub fn new(config: TileMapConfig) -> Self {
         Self {
-            tiles: HashMap::new(),
+            tiles: ChunkedTiles::new(config.chunk_size),
             config,
+            origin: GlobalTransform::IDENTITY,
+            root: None,
             _marker: PhantomData,
         }
     }
@@ -28,52 +43,163 @@ impl<MARKER: MarkerAble> TileMap<MARKER> {
         let orientation = orientation as usize % len;
         self.config.possible_orientations[orientation]
     }
+    /// World position of the center of the tile cell at `map_pos`.
+    pub fn map_to_world(&self, map_pos: IVec3) -> Vec3 {
+        self.origin
+            .transform_point(self.config.grid_to_world(map_pos))
+    }
+    /// Transform of `tile` relative to the map root, at the center of its cell and turned by
+    /// its orientation.
+    pub fn tile_transform(&self, tile: &Tile<MARKER>) -> Transform {
+        Transform::from_translation(self.config.grid_to_world(tile.map_pos))
+            .with_rotation(self.orientation_to_rotation(tile.orientation))
+    }
+    /// Map position of the tile cell containing the world position.
+    pub fn world_to_map(&self, world_pos: Vec3) -> IVec3 {
+        let local_pos = self.origin.affine().inverse().transform_point3(world_pos);
+        self.config.world_to_grid(local_pos)
+    }
+    /// World direction of the face of a cell towards its neighbor at `offset`.
+    pub fn face_normal(&self, offset: IVec3) -> Vec3 {
+        let normal = self
+            .config
+            .topology
+            .face_normal(offset, self.config.tile_size);
+        // Normals follow the inverse transpose to stay perpendicular under non-uniform scale
+        let normal_matrix = Mat3::from(self.origin.affine().matrix3)
+            .inverse()
+            .transpose();
+        (normal_matrix * normal).normalize_or_zero()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
 pub struct TileMapConfig {
+    /// Not saved, set it again when loading a config.
+    #[serde(skip)]
+    pub topology: SharedTopology,
     pub tile_size: Vec3,
     pub source_path: String,
     pub file_format: utils::FileFormat,
     pub load_from_source: bool,
+    /// Face normals and neighbor offsets, filled from the topology.
     pub next_tile_dir: Vec<(Vec3, IVec3)>,
+    /// Filled from the topology.
     pub possible_orientations: Vec<Quat>,
+    /// Number of tiles along each axis of a chunk.
+    pub chunk_size: IVec3,
+    /// How [`TileMapCommands`](crate::map_commands::TileMapCommands) treats occupied cells.
+    pub occupied_policy: OccupiedPolicy,
+    /// Initial seed of the [`TileMapRng`](crate::rng::TileMapRng), until a map with a seed is loaded.
+    pub seed: u64,
 }
 impl TileMapConfig {
+    /// Also recomputes the face normals of `next_tile_dir`, which depend on the tile size.
     pub fn with_tile_size(mut self, size: impl Into<Vec3>) -> Self {
         self.tile_size = size.into();
+        self.update_face_normals();
         self
     }
     pub fn with_source_path(mut self, path: impl Into<String>) -> Self {
         self.source_path = path.into();
         self
     }
+    pub fn with_chunk_size(mut self, size: impl Into<IVec3>) -> Self {
+        self.chunk_size = size.into();
+        self
+    }
+    pub fn with_occupied_policy(mut self, policy: OccupiedPolicy) -> Self {
+        self.occupied_policy = policy;
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
     pub fn fresh_world(mut self) -> Self {
         self.load_from_source = false;
         self
     }
+    /// Switches to `topology`, taking its neighbors and orientations.
+    pub fn with_topology(mut self, topology: impl GridTopology) -> Self {
+        self.next_tile_dir = topology
+            .neighbors()
+            .into_iter()
+            .map(|offset| (Vec3::ZERO, offset))
+            .collect();
+        self.possible_orientations = topology.orientations();
+        self.topology = SharedTopology(Arc::new(topology));
+        self.update_face_normals();
+        self
+    }
+    /// Fills the normals of `next_tile_dir` from the topology and the current tile size.
+    fn update_face_normals(&mut self) {
+        let (topology, tile_size) = (&self.topology, self.tile_size);
+        self.next_tile_dir
+            .iter_mut()
+            .for_each(|(normal, offset)| *normal = topology.face_normal(*offset, tile_size));
+    }
+    /// Replaces the orientations given by the topology.
+    pub fn with_orientations(mut self, orientations: Vec<Quat>) -> Self {
+        self.possible_orientations = orientations;
+        self
+    }
+    /// Allows all 24 cube rotations, for walls, ceilings and sideways tiles. See
+    /// [`CuboidGrid::all_rotations`].
+    pub fn with_all_cube_orientations(self) -> Self {
+        self.with_orientations(CuboidGrid::all_rotations())
+    }
+    /// Switches to [`HexPrismGrid`]. Keeps the tile size, which should be about
+    /// `(w, h, w * 2 / √3)` for regular hexagons.
+    pub fn hex_prism(self) -> Self {
+        self.with_topology(HexPrismGrid)
+    }
+
+    /// World position of the center of the cell at `map_pos`.
+    pub fn grid_to_world(&self, map_pos: IVec3) -> Vec3 {
+        self.topology.grid_to_world(map_pos, self.tile_size)
+    }
+    /// Map position of the cell containing the world position.
+    pub fn world_to_grid(&self, world_pos: Vec3) -> IVec3 {
+        self.topology.world_to_grid(world_pos, self.tile_size)
+    }
+    /// `offset` turned by one of the `possible_orientations`.
+    pub fn rotate_offset(&self, rotation: Quat, offset: IVec3) -> IVec3 {
+        self.topology.rotate_offset(rotation, offset)
+    }
+    /// Number of steps between two cells, moving only to direct neighbors.
+    pub fn grid_distance(&self, a: IVec3, b: IVec3) -> i32 {
+        self.topology.grid_distance(a, b)
+    }
 }
+
 impl Default for TileMapConfig {
     fn default() -> Self {
         Self {
+            topology: SharedTopology::default(),
             tile_size: (1.0, 1.0, 1.0).into(),
             source_path: "tile_map".into(),
-            file_format: utils::FileFormat::Csv,
+            file_format: utils::FileFormat::default(),
             load_from_source: true,
-            next_tile_dir: vec![
-                (Vec3::X, IVec3::X),
-                (Vec3::Z, IVec3::Z),
-                (Vec3::NEG_X, IVec3::NEG_X),
-                (Vec3::NEG_Z, IVec3::NEG_Z),
-                (Vec3::Y, IVec3::Y),
-                (Vec3::NEG_Y, IVec3::NEG_Y),
-            ],
-            possible_orientations: vec![
-                Quat::from_rotation_y(PI / 2.0 * 0.0),
-                Quat::from_rotation_y(PI / 2.0 * 1.0),
-                Quat::from_rotation_y(PI / 2.0 * 2.0),
-                Quat::from_rotation_y(PI / 2.0 * 3.0),
-            ],
+            next_tile_dir: Vec::new(),
+            possible_orientations: Vec::new(),
+            chunk_size: IVec3::splat(16),
+            occupied_policy: OccupiedPolicy::Replace,
+            seed: 0,
         }
+        .with_topology(CuboidGrid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_normals_follow_the_tile_size() {
+        let size = Vec3::new(16.0, 8.0, 16.0 * 2.0 / 3f32.sqrt());
+        let before = TileMapConfig::default().with_tile_size(size).hex_prism();
+        let after = TileMapConfig::default().hex_prism().with_tile_size(size);
+        assert_eq!(before.next_tile_dir, after.next_tile_dir);
     }
 }
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::marker::PhantomData;
+
+use crate::marker_traits::MarkerAble;
+
+/// Seeded random numbers for editing a map, so the same seed and edits give the same map. The
+/// seed is saved with the map and restored by [`LoadMapEvent`](crate::events::LoadMapEvent).
+///
+/// Only the seed is saved, not the position in the stream: loading a map reseeds, so the
+/// stream starts over instead of continuing where it was when the map was saved.
+#[derive(Debug, Resource)]
+pub struct TileMapRng<MARKER: MarkerAble> {
+    seed: u64,
+    rng: StdRng,
+    /// Relative chance of every orientation, all orientations are equally likely if empty.
+    pub orientation_weights: Vec<f32>,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> TileMapRng<MARKER> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            orientation_weights: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+    pub fn with_orientation_weights(mut self, weights: Vec<f32>) -> Self {
+        self.orientation_weights = weights;
+        self
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Starts over with a new seed. Reseeding with the same seed repeats the same choices.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Random orientation out of `count`, weighted by `orientation_weights`.
+    pub fn orientation(&mut self, count: usize) -> u8 {
+        if count == 0 {
+            return 0;
+        }
+        let weights = &self.orientation_weights;
+        if weights.len() >= count && weights[..count].iter().any(|weight| *weight > 0.0) {
+            let options: Vec<(u8, f32)> = (0..count).map(|o| (o as u8, weights[o])).collect();
+            if let Some(orientation) = self.choose_weighted(&options) {
+                return *orientation;
+            }
+        }
+        self.rng.random_range(0..count) as u8
+    }
+    /// Random entry of `options`, weighted by the second value. Entries with a weight of zero or
+    /// less are never picked.
+    pub fn choose_weighted<'a, T>(&mut self, options: &'a [(T, f32)]) -> Option<&'a T> {
+        let total: f32 = options.iter().map(|(_, weight)| weight.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = self.rng.random::<f32>() * total;
+        let mut last = None;
+        for (option, weight) in options.iter().filter(|(_, weight)| *weight > 0.0) {
+            pick -= weight;
+            last = Some(option);
+            if pick < 0.0 {
+                break;
+            }
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reseeding_repeats_the_choices() {
+        let options = [("a", 1.0), ("b", 2.0), ("c", 0.0), ("d", 0.5)];
+        let mut rng = TileMapRng::<()>::new(1).with_orientation_weights(vec![1.0, 0.0, 3.0, 1.0]);
+        let choices = |rng: &mut TileMapRng<()>| -> Vec<(u8, &str)> {
+            (0..32)
+                .map(|_| (rng.orientation(4), *rng.choose_weighted(&options).unwrap()))
+                .collect()
+        };
+
+        rng.reseed(42);
+        let first = choices(&mut rng);
+        rng.reseed(42);
+        assert_eq!(first, choices(&mut rng));
+        assert_eq!(rng.seed(), 42);
+        assert!(
+            first
+                .iter()
+                .all(|(orientation, typ)| *orientation != 1 && *typ != "c")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+
+use crate::components::Tile;
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+use crate::utils;
+
+/// Aggregated information about a tile map, recomputed whenever tiles change.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct TileMapStats<MARKER: MarkerAble> {
+    pub tile_count: usize,
+    /// Number of tiles per `TYP` variant name.
+    pub type_counts: BTreeMap<String, usize>,
+    /// Number of tiles per orientation index.
+    pub orientation_counts: BTreeMap<u8, usize>,
+    /// Smallest and largest occupied map position.
+    pub bounds: Option<(IVec3, IVec3)>,
+    /// Number of groups of tiles not connected through `next_tile_dir`.
+    pub island_count: usize,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> Default for TileMapStats<MARKER> {
+    fn default() -> Self {
+        Self {
+            tile_count: 0,
+            type_counts: BTreeMap::new(),
+            orientation_counts: BTreeMap::new(),
+            bounds: None,
+            island_count: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<MARKER: MarkerAble> TileMapStats<MARKER> {
+    pub fn compute<'a, TYP: TileTypeAble>(
+        tile_map: &TileMap<MARKER>,
+        tiles: impl IntoIterator<Item = (&'a TYP, &'a Tile<MARKER>)>,
+    ) -> Self {
+        let mut stats = Self::default();
+        for (typ, tile) in tiles {
+            stats.tile_count += 1;
+            *stats
+                .type_counts
+                .entry(utils::variant_name(typ))
+                .or_default() += 1;
+            *stats
+                .orientation_counts
+                .entry(tile.orientation)
+                .or_default() += 1;
+            stats.bounds = Some(match stats.bounds {
+                Some((min, max)) => (min.min(tile.map_pos), max.max(tile.map_pos)),
+                None => (tile.map_pos, tile.map_pos),
+            });
+        }
+        stats.island_count = Self::count_islands(tile_map);
+        stats
+    }
+    fn count_islands(tile_map: &TileMap<MARKER>) -> usize {
+        let mut visited: HashSet<IVec3> = HashSet::new();
+        let mut islands = 0;
+        for start in tile_map.tiles.keys() {
+            if !visited.insert(*start) {
+                continue;
+            }
+            islands += 1;
+            let mut queue = VecDeque::from([*start]);
+            while let Some(pos) = queue.pop_front() {
+                for (_, delta) in tile_map.config.next_tile_dir.iter() {
+                    let next = pos + *delta;
+                    if tile_map.tiles.contains_key(&next) && visited.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        islands
+    }
+    pub fn update<TYP: TileTypeAble>(
+        mut stats: ResMut<Self>,
+        tile_map: Res<TileMap<MARKER>>,
+        tiles: Query<(&TYP, &Tile<MARKER>)>,
+        changed_types: Query<(), Changed<TYP>>,
+        changed_tiles: Query<(), Changed<Tile<MARKER>>>,
+        mut removed: RemovedComponents<Tile<MARKER>>,
+    ) {
+        let removed = removed.read().count() > 0;
+        if !tile_map.is_changed()
+            && changed_types.is_empty()
+            && changed_tiles.is_empty()
+            && !removed
+        {
+            return;
+        }
+        *stats = Self::compute(&tile_map, tiles.iter());
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on, poll_once};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use crate::events::spawn_tiles;
+use crate::io::{self, TileRecord};
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::resources::TileMap;
+use crate::tile_data::TileDataRegistry;
+
+/// Loads the chunks around this entity from the chunk files written by
+/// [`SaveMapEvent::chunked`](crate::events::SaveMapEvent::chunked) and unloads them once the
+/// entity moved away. Unloading despawns the tiles, unsaved edits in those chunks are lost.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct TileMapStreamer<MARKER: MarkerAble> {
+    /// Chunks within this distance (in chunks) around the entity get loaded.
+    pub load_radius: i32,
+    /// Loaded chunks further away than this get unloaded again.
+    pub unload_radius: i32,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> Default for TileMapStreamer<MARKER> {
+    fn default() -> Self {
+        Self::new(2, 3)
+    }
+}
+impl<MARKER: MarkerAble> TileMapStreamer<MARKER> {
+    pub fn new(load_radius: i32, unload_radius: i32) -> Self {
+        Self {
+            load_radius,
+            unload_radius: unload_radius.max(load_radius),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Chunks loaded by the [`TileMapStreamer`]s and the chunk files currently being read.
+#[derive(Resource)]
+pub struct TileMapStreaming<TYP: TileTypeAble, MARKER: MarkerAble> {
+    loaded: HashSet<IVec3>,
+    pending: HashMap<IVec3, Task<Vec<TileRecord<TYP>>>>,
+    streamed: bool,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileMapStreaming<TYP, MARKER> {
+    fn default() -> Self {
+        Self {
+            loaded: HashSet::new(),
+            pending: HashMap::new(),
+            streamed: false,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileMapStreaming<TYP, MARKER> {
+    pub fn is_loaded(&self, chunk: IVec3) -> bool {
+        self.loaded.contains(&chunk)
+    }
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = &IVec3> {
+        self.loaded.iter()
+    }
+    /// Whether chunks were streamed in, so the tiles in memory may be only part of the map.
+    pub fn is_streaming(&self) -> bool {
+        self.streamed
+    }
+
+    pub fn update(
+        mut streaming: ResMut<Self>,
+        streamers: Query<(&GlobalTransform, &TileMapStreamer<MARKER>)>,
+        tile_map: Res<TileMap<MARKER>>,
+        tile_data: Res<TileDataRegistry<MARKER>>,
+        mut commands: Commands,
+    ) {
+        let focus: Vec<(IVec3, &TileMapStreamer<MARKER>)> = streamers
+            .iter()
+            .map(|(transform, streamer)| {
+                let map_pos = tile_map.world_to_map(transform.translation());
+                (tile_map.tiles.chunk_of(map_pos), streamer)
+            })
+            .collect();
+        let distance = |a: IVec3, b: IVec3| (a - b).abs().max_element();
+        let keep = |chunk: IVec3| {
+            focus
+                .iter()
+                .any(|(center, streamer)| distance(chunk, *center) <= streamer.unload_radius)
+        };
+
+        // Unload chunks out of range and drop reads that aren't needed anymore
+        let unload: Vec<IVec3> = streaming
+            .loaded
+            .iter()
+            .copied()
+            .filter(|c| !keep(*c))
+            .collect();
+        unload.iter().for_each(|chunk| {
+            streaming.loaded.remove(chunk);
+            tile_map
+                .tiles
+                .tiles_in_chunk(*chunk)
+                .for_each(|(_, entity)| commands.entity(*entity).despawn());
+        });
+        streaming.pending.retain(|chunk, _| keep(*chunk));
+
+        // Start reading chunks coming into range
+        let format = tile_map.config.file_format;
+        for (center, streamer) in focus.iter() {
+            let r = streamer.load_radius;
+            for x in -r..=r {
+                for y in -r..=r {
+                    for z in -r..=r {
+                        let chunk = *center + IVec3::new(x, y, z);
+                        if streaming.loaded.contains(&chunk)
+                            || streaming.pending.contains_key(&chunk)
+                        {
+                            continue;
+                        }
+                        let path = io::chunk_path(&tile_map.config.source_path, chunk, format);
+                        let task = IoTaskPool::get().spawn(async move {
+                            // A missing chunk file just means the chunk is empty
+                            io::read_tiles_from_file::<TYP>(&path, format).unwrap_or_default()
+                        });
+                        streaming.pending.insert(chunk, task);
+                        streaming.streamed = true;
+                    }
+                }
+            }
+        }
+
+        // Spawn the tiles of finished reads
+        let mut finished = Vec::new();
+        streaming
+            .pending
+            .retain(|chunk, task| match block_on(poll_once(task)) {
+                Some(tiles) => {
+                    finished.push((*chunk, tiles));
+                    false
+                }
+                None => true,
+            });
+        finished.into_iter().for_each(|(chunk, tiles)| {
+            streaming.loaded.insert(chunk);
+            spawn_tiles(&mut commands, &tile_map, None, &tile_data, tiles);
+        });
+    }
+}
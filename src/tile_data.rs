@@ -0,0 +1,112 @@
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use crate::marker_traits::{MarkerAble, TileDataAble};
+
+/// Saves, loads and edits one registered [`TileDataAble`] type.
+#[derive(Debug, Clone)]
+pub struct TileDataEntry {
+    pub key: String,
+    save: fn(&EntityRef) -> Option<Value>,
+    load: fn(&mut EntityCommands, Value) -> Result<(), String>,
+    register: fn(&mut App),
+    #[cfg(feature = "editor")]
+    edit: fn(&mut World, Entity, &mut egui::Ui),
+}
+impl TileDataEntry {
+    pub fn new<D: TileDataAble>() -> Self {
+        Self {
+            key: D::data_key(),
+            save: save_data::<D>,
+            load: load_data::<D>,
+            register: |app| {
+                app.register_type::<D>();
+            },
+            #[cfg(feature = "editor")]
+            edit: edit_data::<D>,
+        }
+    }
+}
+
+/// Tile data types saved and loaded with the map of `MARKER`.
+#[derive(Debug, Resource)]
+pub struct TileDataRegistry<MARKER: MarkerAble> {
+    pub entries: Vec<TileDataEntry>,
+    _marker: PhantomData<MARKER>,
+}
+impl<MARKER: MarkerAble> TileDataRegistry<MARKER> {
+    pub fn new(entries: Vec<TileDataEntry>) -> Self {
+        Self {
+            entries,
+            _marker: PhantomData,
+        }
+    }
+    /// The registered data of the tile, by key.
+    pub fn save(&self, tile: &EntityRef) -> BTreeMap<String, Value> {
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((entry.key.clone(), (entry.save)(tile)?)))
+            .collect()
+    }
+    /// Inserts the data components, skipping unknown keys and values that don't parse.
+    pub fn load(&self, tile: &mut EntityCommands, data: BTreeMap<String, Value>) {
+        for (key, value) in data {
+            let Some(entry) = self.entries.iter().find(|entry| entry.key == key) else {
+                println!("no tile data registered as {}, skipping it", key);
+                continue;
+            };
+            if let Err(e) = (entry.load)(tile, value) {
+                println!("couldn't load tile data {}: {}", key, e);
+            }
+        }
+    }
+    /// Registers the data types for reflection.
+    pub fn register_types(&self, app: &mut App) {
+        for entry in self.entries.iter() {
+            (entry.register)(app);
+        }
+    }
+    /// Widgets to add, edit and remove each registered data of the tile.
+    #[cfg(feature = "editor")]
+    pub fn edit_ui(&self, world: &mut World, tile: Entity, ui: &mut egui::Ui) {
+        for entry in self.entries.iter() {
+            (entry.edit)(world, tile, ui);
+        }
+    }
+}
+
+fn save_data<D: TileDataAble>(tile: &EntityRef) -> Option<Value> {
+    serde_json::to_value(tile.get::<D>()?).ok()
+}
+fn load_data<D: TileDataAble>(tile: &mut EntityCommands, value: Value) -> Result<(), String> {
+    let data: D = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    tile.insert(data);
+    Ok(())
+}
+#[cfg(feature = "editor")]
+fn edit_data<D: TileDataAble>(world: &mut World, tile: Entity, ui: &mut egui::Ui) {
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let Ok(mut tile) = world.get_entity_mut(tile) else {
+        return;
+    };
+    ui.collapsing(D::data_key(), |ui| match tile.get::<D>().cloned() {
+        Some(mut data) => {
+            let type_registry = type_registry.read();
+            // Only insert on edits, to keep change detection quiet
+            if bevy_inspector_egui::reflect_inspector::ui_for_value(&mut data, ui, &type_registry) {
+                tile.insert(data);
+            }
+            if ui.button("Remove").clicked() {
+                tile.remove::<D>();
+            }
+        }
+        None => {
+            if ui.button("Add").clicked() {
+                tile.insert(D::default());
+            }
+        }
+    });
+}
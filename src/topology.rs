@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Shape and arrangement of the tile cells of a map.
+///
+/// Implement it to plug in other layouts, like isometric, staggered or triangular grids, and
+/// set it with [`TileMapConfig::with_topology`](crate::resources::TileMapConfig::with_topology).
+pub trait GridTopology: Debug + Send + Sync + 'static {
+    /// Unique name of the topology, also used to compare configs.
+    fn name(&self) -> &'static str;
+    /// World position of the center of the cell at `map_pos`.
+    fn grid_to_world(&self, map_pos: IVec3, tile_size: Vec3) -> Vec3;
+    /// Map position of the cell containing the world position.
+    fn world_to_grid(&self, world_pos: Vec3, tile_size: Vec3) -> IVec3;
+    /// Offsets to the cells sharing a face with a cell.
+    fn neighbors(&self) -> Vec<IVec3>;
+    /// Outward world direction of the face towards the neighbor at `offset`.
+    fn face_normal(&self, offset: IVec3, tile_size: Vec3) -> Vec3 {
+        (self.grid_to_world(offset, tile_size) - self.grid_to_world(IVec3::ZERO, tile_size))
+            .normalize_or_zero()
+    }
+    /// Rotations a tile can be placed with, the index being its orientation.
+    fn orientations(&self) -> Vec<Quat>;
+    /// `offset` turned by one of the `orientations`.
+    fn rotate_offset(&self, rotation: Quat, offset: IVec3) -> IVec3;
+    /// Number of steps between two cells, moving only to neighbors.
+    fn grid_distance(&self, a: IVec3, b: IVec3) -> i32;
+    /// Whether cells are axis aligned boxes of `tile_size`, which allows exact grid raycasts.
+    fn is_cuboid(&self) -> bool {
+        false
+    }
+}
+
+/// [`GridTopology`] shared between configs, compared by name.
+#[derive(Debug, Clone)]
+pub struct SharedTopology(pub Arc<dyn GridTopology>);
+impl Default for SharedTopology {
+    fn default() -> Self {
+        Self(Arc::new(CuboidGrid))
+    }
+}
+impl PartialEq for SharedTopology {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.name() == other.0.name()
+    }
+}
+impl Deref for SharedTopology {
+    type Target = dyn GridTopology;
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+/// Boxes of `tile_size`, turned in quarter turns around +Y.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CuboidGrid;
+impl CuboidGrid {
+    /// All 24 rotations of a cube: for each direction the tile's +Y can point to, four turns
+    /// around it. The first four are the default quarter turns around +Y, so their indices
+    /// stay valid.
+    pub fn all_rotations() -> Vec<Quat> {
+        [
+            Vec3::Y,
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Z,
+            Vec3::NEG_Z,
+            Vec3::NEG_Y,
+        ]
+        .iter()
+        .flat_map(|up| {
+            // The arc between opposite vectors has no preferred axis, so pick +X
+            let tilt = match *up == Vec3::NEG_Y {
+                true => Quat::from_rotation_x(PI),
+                false => Quat::from_rotation_arc(Vec3::Y, *up),
+            };
+            (0..4).map(move |i| tilt * Quat::from_rotation_y(PI / 2.0 * i as f32))
+        })
+        .collect()
+    }
+}
+impl GridTopology for CuboidGrid {
+    fn name(&self) -> &'static str {
+        "cuboid"
+    }
+    fn grid_to_world(&self, map_pos: IVec3, tile_size: Vec3) -> Vec3 {
+        map_pos.as_vec3() * tile_size
+    }
+    fn world_to_grid(&self, world_pos: Vec3, tile_size: Vec3) -> IVec3 {
+        (world_pos / tile_size).round().as_ivec3()
+    }
+    fn neighbors(&self) -> Vec<IVec3> {
+        vec![
+            IVec3::X,
+            IVec3::Z,
+            IVec3::NEG_X,
+            IVec3::NEG_Z,
+            IVec3::Y,
+            IVec3::NEG_Y,
+        ]
+    }
+    fn face_normal(&self, offset: IVec3, _tile_size: Vec3) -> Vec3 {
+        offset.as_vec3().normalize_or_zero()
+    }
+    fn orientations(&self) -> Vec<Quat> {
+        (0..4)
+            .map(|i| Quat::from_rotation_y(PI / 2.0 * i as f32))
+            .collect()
+    }
+    fn rotate_offset(&self, rotation: Quat, offset: IVec3) -> IVec3 {
+        (rotation * offset.as_vec3()).round().as_ivec3()
+    }
+    fn grid_distance(&self, a: IVec3, b: IVec3) -> i32 {
+        (b - a).abs().element_sum()
+    }
+    fn is_cuboid(&self) -> bool {
+        true
+    }
+}
+
+/// Pointy top hexagonal prisms in axial coordinates: `x` is the column `q`, `z` the row `r`
+/// and `y` the layer. `tile_size.x` is the width across flats and `tile_size.z` the width
+/// across corners, about `(w, h, w * 2 / √3)` for regular hexagons.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HexPrismGrid;
+impl GridTopology for HexPrismGrid {
+    fn name(&self) -> &'static str {
+        "hex_prism"
+    }
+    fn grid_to_world(&self, map_pos: IVec3, tile_size: Vec3) -> Vec3 {
+        let pos = map_pos.as_vec3();
+        Vec3::new(
+            tile_size.x * (pos.x + pos.z * 0.5),
+            tile_size.y * pos.y,
+            tile_size.z * 0.75 * pos.z,
+        )
+    }
+    fn world_to_grid(&self, world_pos: Vec3, tile_size: Vec3) -> IVec3 {
+        let r = world_pos.z / (tile_size.z * 0.75);
+        let q = world_pos.x / tile_size.x - r * 0.5;
+        let (q, r) = hex_round(q, r);
+        IVec3::new(q, (world_pos.y / tile_size.y).round() as i32, r)
+    }
+    fn neighbors(&self) -> Vec<IVec3> {
+        vec![
+            IVec3::new(1, 0, 0),
+            IVec3::new(1, 0, -1),
+            IVec3::new(0, 0, -1),
+            IVec3::new(-1, 0, 0),
+            IVec3::new(-1, 0, 1),
+            IVec3::new(0, 0, 1),
+            IVec3::Y,
+            IVec3::NEG_Y,
+        ]
+    }
+    fn orientations(&self) -> Vec<Quat> {
+        (0..6)
+            .map(|i| Quat::from_rotation_y(PI / 3.0 * i as f32))
+            .collect()
+    }
+    fn rotate_offset(&self, rotation: Quat, offset: IVec3) -> IVec3 {
+        // Number of 60° steps around +Y
+        let turned = rotation * Vec3::X;
+        let angle = (-turned.z).atan2(turned.x);
+        let steps = (angle / (PI / 3.0)).round() as i32;
+        let (mut q, mut r) = (offset.x, offset.z);
+        for _ in 0..steps.rem_euclid(6) {
+            // (q, r, s) -> (-s, -q, -r) with s = -q - r
+            (q, r) = (q + r, -q);
+        }
+        IVec3::new(q, offset.y, r)
+    }
+    fn grid_distance(&self, a: IVec3, b: IVec3) -> i32 {
+        let delta = b - a;
+        let s = -delta.x - delta.z;
+        (delta.x.abs() + delta.z.abs() + s.abs()) / 2 + delta.y.abs()
+    }
+}
+
+/// Rounds fractional axial coordinates to the hexagon containing them.
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i32, rr as i32)
+}
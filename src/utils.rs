@@ -1,9 +1,41 @@
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
 pub enum FileFormat {
     Json,
-    #[default]
     Csv,
 }
+/// The first of the [`enabled`](FileFormat::enabled) formats, Csv if none is.
+impl Default for FileFormat {
+    fn default() -> Self {
+        Self::enabled().first().copied().unwrap_or(Self::Csv)
+    }
+}
+impl FileFormat {
+    /// Formats the crate was built with, see the `csv` and `json` features.
+    pub fn enabled() -> Vec<Self> {
+        [
+            (Self::Csv, cfg!(feature = "csv")),
+            (Self::Json, cfg!(feature = "json")),
+        ]
+        .into_iter()
+        .filter_map(|(format, enabled)| enabled.then_some(format))
+        .collect()
+    }
+}
+
+/// Name of the enum variant `typ` serializes to, or its full Json form for non-enum types.
+pub fn variant_name<T: Serialize>(typ: &T) -> String {
+    match serde_json::to_value(typ) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(serde_json::Value::Object(map)) if map.len() == 1 => map.keys().next().unwrap().clone(),
+        Ok(value) => value.to_string(),
+        Err(_) => "?".into(),
+    }
+}
+
+/// Json form of `typ`, used to key tables by tile type.
+pub fn type_key<T: Serialize>(typ: &T) -> String {
+    serde_json::to_string(typ).unwrap_or_else(|_| "?".into())
+}
@@ -0,0 +1,227 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::components::{Tile, TileOfMap};
+use crate::marker_traits::{MarkerAble, TileTypeAble};
+use crate::render::TileMeshes;
+use crate::resources::TileMap;
+use crate::utils;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileVisualSource {
+    Scene(Handle<Scene>),
+    Mesh {
+        mesh: Handle<Mesh>,
+        material: Handle<StandardMaterial>,
+    },
+}
+
+/// What gets spawned as child of a tile to show it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileVisual {
+    pub source: TileVisualSource,
+    /// Transform of the visual relative to the tile's cell.
+    pub offset: Transform,
+}
+impl TileVisual {
+    pub fn scene(scene: Handle<Scene>) -> Self {
+        Self {
+            source: TileVisualSource::Scene(scene),
+            offset: Transform::IDENTITY,
+        }
+    }
+    pub fn mesh(mesh: Handle<Mesh>, material: Handle<StandardMaterial>) -> Self {
+        Self {
+            source: TileVisualSource::Mesh { mesh, material },
+            offset: Transform::IDENTITY,
+        }
+    }
+    pub fn with_offset(mut self, offset: Transform) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.offset.translation = translation;
+        self
+    }
+}
+
+type TileVisualFn<TYP> = dyn Fn(&TYP, &AssetServer) -> Option<TileVisual> + Send + Sync;
+
+/// Registry of the visuals of each tile type. Entries of the table take precedence over the
+/// function.
+#[derive(Resource)]
+pub struct TileVisuals<TYP: TileTypeAble> {
+    function: Option<Arc<TileVisualFn<TYP>>>,
+    table: HashMap<String, TileVisual>,
+}
+impl<TYP: TileTypeAble> Default for TileVisuals<TYP> {
+    fn default() -> Self {
+        Self {
+            function: None,
+            table: HashMap::new(),
+        }
+    }
+}
+impl<TYP: TileTypeAble> TileVisuals<TYP> {
+    pub fn insert(&mut self, typ: &TYP, visual: TileVisual) {
+        self.table.insert(utils::type_key(typ), visual);
+    }
+    pub fn remove(&mut self, typ: &TYP) -> Option<TileVisual> {
+        self.table.remove(&utils::type_key(typ))
+    }
+    pub fn get(&self, typ: &TYP, asset_server: &AssetServer) -> Option<TileVisual> {
+        if let Some(visual) = self.table.get(&utils::type_key(typ)) {
+            return Some(visual.clone());
+        }
+        self.function.as_ref()?(typ, asset_server)
+    }
+}
+
+/// Child entity of a tile holding its visual.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct TileVisualChild<MARKER: MarkerAble> {
+    typ_key: String,
+    _marker: PhantomData<MARKER>,
+}
+
+/// Places tiles in the world and spawns their visuals from the [`TileVisuals`] registry whenever
+/// tiles are placed, repainted, rotated or moved.
+pub struct TileVisualsPlugin<TYP: TileTypeAble, MARKER: MarkerAble> {
+    function: Option<Arc<TileVisualFn<TYP>>>,
+    _marker: PhantomData<MARKER>,
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Default for TileVisualsPlugin<TYP, MARKER> {
+    fn default() -> Self {
+        Self {
+            function: None,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> TileVisualsPlugin<TYP, MARKER> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Looks up the visual of types without an entry in the table.
+    pub fn with_function(
+        mut self,
+        function: impl Fn(&TYP, &AssetServer) -> Option<TileVisual> + Send + Sync + 'static,
+    ) -> Self {
+        self.function = Some(Arc::new(function));
+        self
+    }
+}
+impl<TYP: TileTypeAble, MARKER: MarkerAble> Plugin for TileVisualsPlugin<TYP, MARKER> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TileVisuals::<TYP> {
+            function: self.function.clone(),
+            table: HashMap::new(),
+        });
+        app.add_systems(
+            PostUpdate,
+            update_tile_visuals::<TYP, MARKER>.before(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// Looks up the visual of a tile type, skipping types drawn by the
+/// [`TileMeshRenderPlugin`](crate::render::TileMeshRenderPlugin) so they aren't drawn twice.
+#[derive(SystemParam)]
+struct VisualLookup<'w, TYP: TileTypeAble> {
+    visuals: Res<'w, TileVisuals<TYP>>,
+    asset_server: Res<'w, AssetServer>,
+    tile_meshes: Option<Res<'w, TileMeshes<TYP>>>,
+}
+impl<TYP: TileTypeAble> VisualLookup<'_, TYP> {
+    fn get(&self, typ: &TYP) -> Option<TileVisual> {
+        if let Some(tile_meshes) = &self.tile_meshes
+            && tile_meshes.get(typ).is_some()
+        {
+            return None;
+        }
+        self.visuals.get(typ, &self.asset_server)
+    }
+}
+
+type ChangedTiles<'w, 's, TYP, MARKER> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Tile<MARKER>,
+        &'static TYP,
+        Option<&'static TileOfMap>,
+        Option<&'static Children>,
+    ),
+    Or<(Changed<Tile<MARKER>>, Changed<TYP>)>,
+>;
+
+fn update_tile_visuals<TYP: TileTypeAble, MARKER: MarkerAble>(
+    tiles: ChangedTiles<TYP, MARKER>,
+    mut visual_children: Query<(&TileVisualChild<MARKER>, &mut Transform)>,
+    visuals: VisualLookup<TYP>,
+    tile_map: Res<TileMap<MARKER>>,
+    maps: Query<&TileMap<MARKER>>,
+    mut commands: Commands,
+) {
+    for (entity, tile, typ, tile_of, children) in tiles.iter() {
+        let transform = match tile_of {
+            Some(TileOfMap(map)) => match maps.get(*map) {
+                Ok(map) => map.tile_transform(tile),
+                Err(_) => continue,
+            },
+            None => tile_map.tile_transform(tile),
+        };
+        // Keep a visibility set by the game, e.g. `Hidden`
+        commands
+            .entity(entity)
+            .insert(transform)
+            .insert_if_new(Visibility::default());
+
+        let typ_key = utils::type_key(typ);
+        let visual = visuals.get(typ);
+        let mut up_to_date = false;
+        for child in children.into_iter().flat_map(|children| children.iter()) {
+            let Ok((visual_child, mut child_transform)) = visual_children.get_mut(child) else {
+                continue;
+            };
+            if visual.is_some() && visual_child.typ_key == typ_key && !up_to_date {
+                // Only the tile moved or turned, the visual can stay
+                if let Some(visual) = &visual {
+                    *child_transform = visual.offset;
+                }
+                up_to_date = true;
+            } else {
+                commands.entity(child).despawn();
+            }
+        }
+        if up_to_date {
+            continue;
+        }
+        let Some(visual) = visual else {
+            continue;
+        };
+        let child = TileVisualChild::<MARKER> {
+            typ_key,
+            _marker: PhantomData,
+        };
+        match visual.source {
+            TileVisualSource::Scene(scene) => {
+                commands.spawn((child, SceneRoot(scene), visual.offset, ChildOf(entity)));
+            }
+            TileVisualSource::Mesh { mesh, material } => {
+                commands.spawn((
+                    child,
+                    Mesh3d(mesh),
+                    MeshMaterial3d(material),
+                    visual.offset,
+                    ChildOf(entity),
+                ));
+            }
+        }
+    }
+}
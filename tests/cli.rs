@@ -0,0 +1,84 @@
+#![cfg(all(feature = "csv", feature = "json"))]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn run(args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bevy_grid_map"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+/// Path of `file` next to the temporary source path of the tests.
+fn temp_file(file: &str) -> PathBuf {
+    Path::new(&common::temp_source_path()).with_file_name(file)
+}
+
+const MAP: &str = "x,y,z,dir,typ,data\n\
+                   seed,5,,,,\n\
+                   0,0,0,0,\"\"\"Stone\"\"\",\n\
+                   1,0,0,3,\"\"\"Water\"\"\",\"{\"\"Lock\"\":3}\"\n";
+
+#[test]
+fn convert_keeps_tiles_and_seed() {
+    let csv = temp_file("map.csv");
+    let json = temp_file("map.json");
+    std::fs::write(&csv, MAP).unwrap();
+
+    let output = run(&["convert".as_ref(), &csv, &json]);
+    assert!(output.status.success(), "{}", stdout(&output));
+
+    let output = run(&["diff".as_ref(), &csv, &json]);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+    assert!(stdout(&output).contains("0 added, 0 removed, 0 changed"));
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let csv = temp_file("broken.csv");
+    let map = format!(
+        "{}0,0,0,1,\"\"\"Water\"\"\",\nq,0,0,0,\"\"\"Stone\"\"\",\n",
+        MAP
+    );
+    std::fs::write(&csv, map).unwrap();
+
+    let output = run(&[
+        "validate".as_ref(),
+        &csv,
+        "--orientations".as_ref(),
+        "2".as_ref(),
+    ]);
+    let report = stdout(&output);
+    assert_eq!(output.status.code(), Some(1), "{}", report);
+    assert!(report.contains("line 6: invalid x \"q\""), "{}", report);
+    assert!(report.contains("2 tiles at [0, 0, 0]"), "{}", report);
+    assert!(report.contains("has orientation 3"), "{}", report);
+    assert!(report.contains("3 problems"), "{}", report);
+}
+
+#[test]
+fn diff_lists_changes() {
+    let old = temp_file("old.csv");
+    let new = temp_file("new.csv");
+    std::fs::write(&old, MAP).unwrap();
+    let changed = MAP
+        .replace("0,0,0,0,\"\"\"Stone\"\"\"", "0,0,0,2,\"\"\"Stone\"\"\"")
+        .replace("1,0,0,3", "2,0,0,3");
+    std::fs::write(&new, changed).unwrap();
+
+    let output = run(&["diff".as_ref(), &old, &new]);
+    let report = stdout(&output);
+    assert_eq!(output.status.code(), Some(1), "{}", report);
+    assert!(report.contains("~ [0, 0, 0] dir 0 -> 2"), "{}", report);
+    assert!(report.contains("- [1, 0, 0]"), "{}", report);
+    assert!(report.contains("+ [2, 0, 0]"), "{}", report);
+    assert!(
+        report.contains("1 added, 1 removed, 1 changed"),
+        "{}",
+        report
+    );
+}
@@ -0,0 +1,112 @@
+//! Headless app and helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use bevy_grid_map::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Component, Reflect,
+)]
+pub enum TestTile {
+    #[default]
+    Ground,
+    Stone,
+    Water,
+}
+impl TileTypeAble for TestTile {}
+
+/// Tile map plugin of the tests.
+pub type TestMapPlugin = TileMapPlugin<TestTile, ()>;
+
+/// Config writing its map files into a fresh temporary directory.
+pub fn test_config() -> TileMapConfig {
+    TileMapConfig::default()
+        .with_source_path(temp_source_path())
+        .fresh_world()
+}
+
+/// Source path inside a directory of its own, so tests running in parallel don't share files.
+pub fn temp_source_path() -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "bevy_grid_map_test_{}_{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("tile_map").to_string_lossy().into_owned()
+}
+
+/// `MinimalPlugins` app running `plugin`, after its startup systems ran.
+pub fn test_app_with(plugin: TestMapPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, plugin));
+    app.update();
+    app
+}
+/// `MinimalPlugins` app with an empty tile map using `config`.
+pub fn test_app(config: TileMapConfig) -> App {
+    test_app_with(TestMapPlugin::new().with_config(config))
+}
+
+/// Runs the schedule `frames` times.
+pub fn step(app: &mut App, frames: usize) {
+    (0..frames).for_each(|_| app.update());
+}
+
+/// Spawns a tile and runs a frame.
+pub fn place(app: &mut App, map_pos: IVec3, typ: TestTile, orientation: u8) -> Entity {
+    let entity = app
+        .world_mut()
+        .spawn((Tile::<()>::new(map_pos, orientation), typ))
+        .id();
+    app.update();
+    entity
+}
+/// Despawns the tile at `map_pos`, if any, and runs a frame.
+pub fn remove(app: &mut App, map_pos: IVec3) -> Option<Entity> {
+    let entity = tile_at(app, map_pos)?;
+    app.world_mut().despawn(entity);
+    app.update();
+    Some(entity)
+}
+/// Triggers `event` and runs a frame, so commands of its observers are applied.
+pub fn trigger<'a, E: Event<Trigger<'a>: Default>>(app: &mut App, event: E) {
+    app.world_mut().trigger(event);
+    app.update();
+}
+
+pub fn tile_map(app: &App) -> &TileMap<()> {
+    app.world().resource::<TileMap<()>>()
+}
+/// Entity in the index of the `TileMap` resource at `map_pos`.
+pub fn tile_at(app: &App, map_pos: IVec3) -> Option<Entity> {
+    tile_map(app).tiles.get(&map_pos).copied()
+}
+/// Position, orientation and type of every tile entity, sorted by position.
+pub fn tiles(app: &mut App) -> Vec<(IVec3, u8, TestTile)> {
+    let mut tiles: Vec<_> = app
+        .world_mut()
+        .query::<(&Tile<()>, &TestTile)>()
+        .iter(app.world())
+        .map(|(tile, typ)| (tile.map_pos, tile.orientation, *typ))
+        .collect();
+    tiles.sort_by_key(|(map_pos, _, _)| map_pos.to_array());
+    tiles
+}
+
+/// Path of the map file the app reads and writes in `format`.
+pub fn map_file(app: &App, format: FileFormat) -> PathBuf {
+    let extension = match format {
+        FileFormat::Json => "json",
+        FileFormat::Csv => "csv",
+    };
+    PathBuf::from(format!(
+        "{}.{}",
+        tile_map(app).config.source_path,
+        extension
+    ))
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "editor")]
+mod common;
+
+use bevy::camera::RenderTargetInfo;
+use bevy::gizmos::GizmoPlugin;
+use bevy::prelude::*;
+use bevy_grid_map::prelude::*;
+use common::*;
+
+/// Editor app with a camera above the origin looking down and the cursor in the middle of the
+/// window, so clicks hit the top of the tile at the origin.
+fn editor_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, AssetPlugin::default()));
+    app.add_plugins(GizmoPlugin);
+    app.init_asset::<Mesh>().init_asset::<StandardMaterial>();
+    app.init_resource::<ButtonInput<MouseButton>>();
+    app.add_plugins(
+        TestMapPlugin::new()
+            .with_config(test_config())
+            .in_edit_mod(),
+    );
+
+    let mut window = Window::default();
+    window.resolution.set(100.0, 100.0);
+    window.set_cursor_position(Some(Vec2::splat(50.0)));
+    app.world_mut().spawn(window);
+
+    let mut camera = Camera::default();
+    // Set by the render app in a windowed build
+    camera.computed.target_info = Some(RenderTargetInfo {
+        physical_size: UVec2::splat(100),
+        scale_factor: 1.0,
+    });
+    let transform = Transform::from_xyz(0.0, 10.0, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z);
+    app.world_mut()
+        .spawn((camera, transform, GlobalTransform::from(transform)));
+
+    app.update();
+    editor(&mut app).random_orientation = false;
+    app
+}
+
+fn editor(app: &mut App) -> Mut<'_, TileMapEditor<TestTile, ()>> {
+    app.world_mut()
+        .resource_mut::<TileMapEditor<TestTile, ()>>()
+}
+
+/// Presses the left mouse button for one frame.
+fn click(app: &mut App) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<MouseButton>>()
+        .press(MouseButton::Left);
+    app.update();
+    app.world_mut()
+        .resource_mut::<ButtonInput<MouseButton>>()
+        .reset_all();
+    app.update();
+}
+
+#[test]
+fn editor_starts_with_a_source_tile() {
+    let mut app = editor_app();
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 0, TestTile::Ground)]);
+}
+
+#[test]
+fn nothing_happens_without_a_click() {
+    let mut app = editor_app();
+    editor(&mut app).mode = EditorMode::Erase;
+    step(&mut app, 3);
+    assert_eq!(tiles(&mut app).len(), 1);
+}
+
+#[test]
+fn paint_replaces_the_clicked_tile() {
+    let mut app = editor_app();
+    {
+        let mut editor = editor(&mut app);
+        editor.mode = EditorMode::Paint;
+        editor.selected_tile_type = TestTile::Stone;
+        editor.selected_orientation = 2;
+    }
+
+    click(&mut app);
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 2, TestTile::Stone)]);
+    assert_eq!(tile_map(&app).tiles.len(), 1);
+}
+
+#[test]
+fn attach_places_on_the_clicked_face() {
+    let mut app = editor_app();
+    {
+        let mut editor = editor(&mut app);
+        editor.mode = EditorMode::Attach;
+        editor.selected_tile_type = TestTile::Water;
+    }
+
+    click(&mut app);
+    click(&mut app);
+
+    assert_eq!(
+        tiles(&mut app),
+        vec![
+            (IVec3::ZERO, 0, TestTile::Ground),
+            (IVec3::Y, 0, TestTile::Water),
+            (IVec3::new(0, 2, 0), 0, TestTile::Water),
+        ]
+    );
+    assert!(tile_at(&app, IVec3::new(0, 2, 0)).is_some());
+}
+
+#[test]
+fn erase_removes_the_clicked_tile() {
+    let mut app = editor_app();
+    place(&mut app, IVec3::Y, TestTile::Stone, 0);
+    editor(&mut app).mode = EditorMode::Erase;
+
+    click(&mut app);
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 0, TestTile::Ground)]);
+    assert_eq!(tile_at(&app, IVec3::Y), None);
+}
+
+#[test]
+fn hovered_tile_follows_the_cursor() {
+    let mut app = editor_app();
+    let top = place(&mut app, IVec3::Y, TestTile::Stone, 0);
+    app.update();
+    assert_eq!(editor(&mut app).hovered_tile, Some(top));
+}
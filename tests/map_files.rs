@@ -0,0 +1,282 @@
+#![cfg(all(feature = "csv", feature = "json"))]
+mod common;
+
+use bevy::prelude::*;
+use bevy_grid_map::prelude::*;
+use common::*;
+
+type Load = LoadMapEvent<TestTile, ()>;
+type Save = SaveMapEvent<TestTile, ()>;
+type Clear = ClearMapEvent<TestTile, ()>;
+
+fn sample_tiles(app: &mut App) {
+    place(app, IVec3::ZERO, TestTile::Ground, 0);
+    place(app, IVec3::new(1, 0, -2), TestTile::Stone, 3);
+    place(app, IVec3::new(-4, 2, 7), TestTile::Water, 1);
+}
+
+#[test]
+fn save_and_load_round_trip_every_format() {
+    for format in FileFormat::enabled() {
+        let config = test_config().with_seed(42);
+        let mut app = test_app(config.clone());
+        sample_tiles(&mut app);
+        let saved = tiles(&mut app);
+        trigger(&mut app, Save::new(format));
+        assert!(map_file(&app, format).exists(), "{} file missing", format);
+
+        let mut loaded_app = test_app(config.with_seed(0));
+        trigger(&mut loaded_app, Load::new(format));
+
+        assert_eq!(tiles(&mut loaded_app), saved, "{} round trip", format);
+        assert_eq!(tile_map(&loaded_app).tiles.len(), saved.len());
+        let seed = loaded_app.world().resource::<TileMapRng<()>>().seed();
+        assert_eq!(seed, 42, "{} seed", format);
+    }
+}
+
+#[test]
+fn maps_load_from_source_on_startup() {
+    let config = test_config();
+    let mut app = test_app(config.clone());
+    sample_tiles(&mut app);
+    trigger(&mut app, Save::new(FileFormat::Csv));
+
+    let mut config = config;
+    config.load_from_source = true;
+    let mut loaded_app = test_app(config);
+    assert_eq!(tiles(&mut loaded_app).len(), 3);
+}
+
+#[test]
+fn malformed_csv_rows_are_skipped() {
+    let mut app = test_app(test_config());
+    let csv = "x,y,z,dir,typ,data\n\
+               seed,7,,,,\n\
+               0,0,0,0,\"\"\"Stone\"\"\",\n\
+               a,0,0,0,\"\"\"Stone\"\"\",\n\
+               1,0,0,0,\"\"\"Lava\"\"\",\n\
+               2,0,0,999,\"\"\"Stone\"\"\",\n\
+               3,0\n\
+               4,0,0,1,\"\"\"Water\"\"\",\n";
+    std::fs::write(map_file(&app, FileFormat::Csv), csv).unwrap();
+
+    trigger(&mut app, Load::new(FileFormat::Csv));
+
+    assert_eq!(
+        tiles(&mut app),
+        vec![
+            (IVec3::ZERO, 0, TestTile::Stone),
+            (IVec3::new(4, 0, 0), 1, TestTile::Water),
+        ]
+    );
+    assert_eq!(app.world().resource::<TileMapRng<()>>().seed(), 7);
+}
+
+#[test]
+fn csv_files_without_data_column_load() {
+    let mut app = test_app(test_config());
+    let csv = "x,y,z,dir,typ\n0,1,0,3,\"\"\"Water\"\"\"\n";
+    std::fs::write(map_file(&app, FileFormat::Csv), csv).unwrap();
+
+    trigger(&mut app, Load::new(FileFormat::Csv));
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::Y, 3, TestTile::Water)]);
+}
+
+#[test]
+fn malformed_json_entries_are_skipped() {
+    let mut app = test_app(test_config());
+    let json = r#"{ "tiles": [
+        { "pos": [0, 0, 0], "dir": 2, "typ": "Water" },
+        { "pos": [1, 0], "dir": 0, "typ": "Water" },
+        { "pos": [2, 0, 0], "dir": -1, "typ": "Water" },
+        { "pos": [3, 0, 0], "dir": 0, "typ": "Lava" },
+        { "pos": [4, 0, 0], "typ": "Water" },
+        "not a tile"
+    ] }"#;
+    std::fs::write(map_file(&app, FileFormat::Json), json).unwrap();
+
+    trigger(&mut app, Load::new(FileFormat::Json));
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 2, TestTile::Water)]);
+}
+
+#[test]
+fn loading_replaces_tiles_in_occupied_cells() {
+    #[derive(Resource, Default)]
+    struct Removed(Vec<TestTile>);
+
+    let mut app = test_app(test_config());
+    app.init_resource::<Removed>();
+    app.add_observer(
+        |event: On<TileRemoved<TestTile, ()>>, mut removed: ResMut<Removed>| {
+            removed.0.push(event.typ);
+        },
+    );
+    place(&mut app, IVec3::ZERO, TestTile::Stone, 0);
+    let csv = "x,y,z,dir,typ,data\n\
+               0,0,0,2,\"\"\"Water\"\"\",\n\
+               0,0,0,1,\"\"\"Ground\"\"\",\n";
+    std::fs::write(map_file(&app, FileFormat::Csv), csv).unwrap();
+
+    trigger(&mut app, Load::new(FileFormat::Csv));
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 1, TestTile::Ground)]);
+    assert_eq!(tile_map(&app).tiles.len(), 1);
+    let removed = &app.world().resource::<Removed>().0;
+    assert_eq!(removed, &vec![TestTile::Stone, TestTile::Water]);
+}
+
+#[test]
+fn invalid_or_missing_files_load_nothing() {
+    let mut app = test_app(test_config());
+    trigger(&mut app, Load::new(FileFormat::Csv));
+    assert!(tiles(&mut app).is_empty());
+
+    std::fs::write(map_file(&app, FileFormat::Json), "{ \"tiles\": [").unwrap();
+    trigger(&mut app, Load::new(FileFormat::Json));
+    assert!(tiles(&mut app).is_empty());
+}
+
+/// Runs frames until the streamer finished loading `chunk`.
+fn stream_in(app: &mut App, chunk: IVec3) {
+    for _ in 0..500 {
+        app.update();
+        if app
+            .world()
+            .resource::<TileMapStreaming<TestTile, ()>>()
+            .is_loaded(chunk)
+        {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    panic!("chunk {} wasn't streamed in", chunk);
+}
+
+#[test]
+fn chunked_save_keeps_files_of_unloaded_chunks() {
+    let config = test_config().with_chunk_size(IVec3::splat(4));
+    let chunk_file =
+        |chunk: IVec3| bevy_grid_map::io::chunk_path(&config.source_path, chunk, FileFormat::Csv);
+    let far = IVec3::new(8, 0, 0);
+    let mut app = test_app(config.clone());
+    place(&mut app, IVec3::ZERO, TestTile::Stone, 0);
+    place(&mut app, far, TestTile::Water, 0);
+    trigger(&mut app, Save::new(FileFormat::Csv).chunked());
+    assert!(chunk_file(IVec3::ZERO).exists());
+    assert!(chunk_file(IVec3::new(2, 0, 0)).exists());
+
+    let mut streamed_app = test_app(config.clone());
+    let streamer = streamed_app
+        .world_mut()
+        .spawn((Transform::default(), TileMapStreamer::<()>::new(0, 0)))
+        .id();
+    stream_in(&mut streamed_app, IVec3::ZERO);
+    streamed_app
+        .world_mut()
+        .entity_mut(streamer)
+        .insert(Transform::from_translation(far.as_vec3()));
+    stream_in(&mut streamed_app, IVec3::new(2, 0, 0));
+    assert_eq!(tiles(&mut streamed_app), vec![(far, 0, TestTile::Water)]);
+
+    trigger(&mut streamed_app, Save::new(FileFormat::Csv).chunked());
+    assert!(chunk_file(IVec3::ZERO).exists(), "unloaded chunk deleted");
+
+    // Loaded chunks that got empty lose their file
+    remove(&mut streamed_app, far);
+    trigger(&mut streamed_app, Save::new(FileFormat::Csv).chunked());
+    assert!(!chunk_file(IVec3::new(2, 0, 0)).exists());
+    assert!(chunk_file(IVec3::ZERO).exists());
+}
+
+#[test]
+fn clear_removes_every_tile() {
+    let mut app = test_app(test_config());
+    sample_tiles(&mut app);
+
+    trigger(&mut app, Clear::new());
+
+    assert!(tiles(&mut app).is_empty());
+    assert!(tile_map(&app).tiles.is_empty());
+}
+
+#[test]
+fn clear_with_source_tile_keeps_one_default_tile() {
+    let mut app = test_app(test_config());
+    sample_tiles(&mut app);
+
+    trigger(&mut app, Clear::new().with_source_tile());
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::ZERO, 0, TestTile::default())]);
+    assert!(tile_at(&app, IVec3::ZERO).is_some());
+    assert_eq!(tile_map(&app).tiles.len(), 1);
+}
+
+#[test]
+fn clear_for_map_leaves_other_maps_alone() {
+    let mut app = test_app(test_config());
+    sample_tiles(&mut app);
+    let map = app
+        .world_mut()
+        .spawn(TileMap::<()>::new(test_config()))
+        .id();
+    app.world_mut().spawn((
+        Tile::<()>::new(IVec3::ZERO, 0),
+        TestTile::Stone,
+        TileOfMap(map),
+    ));
+    app.update();
+
+    trigger(&mut app, Clear::new().for_map(map));
+
+    assert_eq!(tiles(&mut app).len(), 3);
+    let map_index = &app.world().get::<TileMap<()>>(map).unwrap().tiles;
+    assert!(map_index.is_empty());
+}
+
+#[test]
+fn loading_into_a_map_entity_leaves_the_resource_alone() {
+    let mut app = test_app(test_config());
+    let resource_tile = place(&mut app, IVec3::ZERO, TestTile::Stone, 0);
+    let config = test_config();
+    let csv = "x,y,z,dir,typ,data\n0,0,0,2,\"\"\"Water\"\"\",\n";
+    std::fs::write(
+        bevy_grid_map::io::map_path(&config.source_path, FileFormat::Csv),
+        csv,
+    )
+    .unwrap();
+    let map = app.world_mut().spawn(TileMap::<()>::new(config)).id();
+    app.update();
+
+    trigger(&mut app, Load::new(FileFormat::Csv).for_map(map));
+
+    assert_eq!(tile_at(&app, IVec3::ZERO), Some(resource_tile));
+    let map_index = &app.world().get::<TileMap<()>>(map).unwrap().tiles;
+    let loaded = *map_index.get(&IVec3::ZERO).unwrap();
+    assert_ne!(loaded, resource_tile);
+    assert_eq!(app.world().get::<TestTile>(loaded), Some(&TestTile::Water));
+}
+
+#[test]
+fn clearing_a_map_entity_with_source_tile_leaves_the_resource_alone() {
+    let mut app = test_app(test_config());
+    let resource_tile = place(&mut app, IVec3::ZERO, TestTile::Stone, 0);
+    let map = app
+        .world_mut()
+        .spawn(TileMap::<()>::new(test_config()))
+        .id();
+    app.update();
+
+    trigger(&mut app, Clear::new().with_source_tile().for_map(map));
+
+    assert_eq!(tile_at(&app, IVec3::ZERO), Some(resource_tile));
+    let map_index = &app.world().get::<TileMap<()>>(map).unwrap().tiles;
+    let source = *map_index.get(&IVec3::ZERO).unwrap();
+    assert_ne!(source, resource_tile);
+    assert_eq!(
+        app.world().get::<TestTile>(source),
+        Some(&TestTile::default())
+    );
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy_grid_map::prelude::*;
+use common::*;
+
+#[test]
+fn placed_tiles_are_indexed() {
+    let mut app = test_app(test_config());
+    let a = place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+    let b = place(&mut app, IVec3::new(3, -1, 2), TestTile::Stone, 1);
+
+    assert_eq!(tile_at(&app, IVec3::ZERO), Some(a));
+    assert_eq!(tile_at(&app, IVec3::new(3, -1, 2)), Some(b));
+    assert_eq!(tile_map(&app).tiles.len(), 2);
+}
+
+#[test]
+fn despawned_tiles_leave_the_index() {
+    let mut app = test_app(test_config());
+    place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+    place(&mut app, IVec3::X, TestTile::Ground, 0);
+
+    assert!(remove(&mut app, IVec3::ZERO).is_some());
+    assert_eq!(tile_at(&app, IVec3::ZERO), None);
+    assert!(tile_at(&app, IVec3::X).is_some());
+    assert_eq!(tile_map(&app).tiles.len(), 1);
+}
+
+#[test]
+fn reinserted_tile_moves_in_the_index() {
+    let mut app = test_app(test_config());
+    let entity = place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+
+    app.world_mut()
+        .entity_mut(entity)
+        .insert(Tile::<()>::new(IVec3::new(0, 1, 0), 2));
+    app.update();
+
+    assert_eq!(tile_at(&app, IVec3::ZERO), None);
+    assert_eq!(tile_at(&app, IVec3::Y), Some(entity));
+}
+
+#[test]
+fn removing_the_tile_component_unindexes_it() {
+    let mut app = test_app(test_config());
+    let entity = place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+
+    app.world_mut().entity_mut(entity).remove::<Tile<()>>();
+    app.update();
+
+    assert_eq!(tile_at(&app, IVec3::ZERO), None);
+    assert!(tile_map(&app).tiles.is_empty());
+}
+
+#[test]
+fn tiles_are_split_into_chunks() {
+    let mut app = test_app(test_config().with_chunk_size(IVec3::splat(4)));
+    place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+    place(&mut app, IVec3::new(5, 0, 0), TestTile::Ground, 0);
+    place(&mut app, IVec3::new(-1, 0, 0), TestTile::Ground, 0);
+
+    let tiles = &tile_map(&app).tiles;
+    assert_eq!(tiles.chunks().count(), 3);
+    assert_eq!(tiles.chunk_of(IVec3::new(-1, 0, 0)), IVec3::new(-1, 0, 0));
+    assert_eq!(tiles.tiles_in_chunk(IVec3::new(1, 0, 0)).count(), 1);
+}
+
+#[test]
+fn map_entity_tiles_use_their_own_index() {
+    let mut app = test_app(test_config());
+    let map = app
+        .world_mut()
+        .spawn(TileMap::<()>::new(test_config()))
+        .id();
+    let tile = app
+        .world_mut()
+        .spawn((
+            Tile::<()>::new(IVec3::ZERO, 0),
+            TestTile::Stone,
+            TileOfMap(map),
+        ))
+        .id();
+    app.update();
+
+    let map_index = &app.world().get::<TileMap<()>>(map).unwrap().tiles;
+    assert_eq!(map_index.get(&IVec3::ZERO), Some(&tile));
+    assert_eq!(tile_at(&app, IVec3::ZERO), None);
+
+    // Despawning the map takes its tiles along
+    app.world_mut().despawn(map);
+    app.update();
+    assert!(app.world().get_entity(tile).is_err());
+}
+
+#[test]
+fn map_commands_keep_one_tile_per_cell() {
+    let mut app = test_app(test_config().with_occupied_policy(OccupiedPolicy::Stack));
+    let first = place(&mut app, IVec3::ZERO, TestTile::Ground, 0);
+
+    let result = app
+        .world_mut()
+        .run_system_once(|mut map_commands: TileMapCommands<TestTile, ()>| {
+            map_commands.place(IVec3::ZERO, TestTile::Stone, 0)
+        })
+        .unwrap();
+    app.update();
+
+    let PlaceResult::Stacked { entity, map_pos } = result else {
+        panic!("expected the tile to be stacked, got {:?}", result);
+    };
+    assert_eq!(map_pos, IVec3::Y);
+    assert_eq!(tile_at(&app, IVec3::ZERO), Some(first));
+    assert_eq!(tile_at(&app, IVec3::Y), Some(entity));
+}
+
+#[test]
+fn moving_a_tile_placed_in_the_same_system_keeps_its_orientation() {
+    let mut app = test_app(test_config());
+
+    app.world_mut()
+        .run_system_once(|mut map_commands: TileMapCommands<TestTile, ()>| {
+            map_commands.place(IVec3::ZERO, TestTile::Stone, 3);
+            map_commands.move_tile(IVec3::ZERO, IVec3::X);
+            map_commands.swap(IVec3::X, IVec3::Z);
+        })
+        .unwrap();
+    app.update();
+
+    assert_eq!(tiles(&mut app), vec![(IVec3::Z, 3, TestTile::Stone)]);
+    assert!(tile_at(&app, IVec3::Z).is_some());
+    assert_eq!(tile_map(&app).tiles.len(), 1);
+}
+
+#[test]
+fn changed_map_entity_tiles_mark_their_own_chunks() {
+    let mut app = test_app(test_config());
+    let map = app
+        .world_mut()
+        .spawn(TileMap::<()>::new(test_config()))
+        .id();
+    let tile = app
+        .world_mut()
+        .spawn((
+            Tile::<()>::new(IVec3::ZERO, 0),
+            TestTile::Stone,
+            TileOfMap(map),
+        ))
+        .id();
+    app.update();
+    let generation = |app: &App| {
+        let map_index = &app.world().get::<TileMap<()>>(map).unwrap().tiles;
+        map_index.chunk(IVec3::ZERO).unwrap().generation
+    };
+    let resource_changed = |app: &App| {
+        let ticks = app.world().get_resource_change_ticks::<TileMap<()>>();
+        ticks.unwrap().changed
+    };
+    let (before, resource_before) = (generation(&app), resource_changed(&app));
+
+    app.world_mut().entity_mut(tile).insert(TestTile::Water);
+    app.update();
+
+    assert!(generation(&app) > before);
+    assert_eq!(resource_changed(&app), resource_before);
+}